@@ -2,7 +2,9 @@ extern crate rustc_serialize;
 
 use std::error::Error as StdError;
 use std::io::Error as IoError;
+use std::time::Duration;
 use hyper::Error as HttpError;
+use hyper::status::StatusCode;
 use url::ParseError;
 use self::rustc_serialize::json::DecoderError;
 
@@ -10,17 +12,34 @@ use std::fmt::{Display, Formatter};
 use std::fmt::Result as FormatResult;
 
 use path::Expectation;
+use graph::Nodes;
 
 use self::Error::{
     InvalidUrl,
     MalformedRequest,
     RequestIoFailed,
     RequestFailed,
+    RequestTimedOut,
+    HttpStatus,
     DecodingFailed,
+    ServerError,
     ResponseParseFailed,
+    ResponseTooLarge,
+    OutputFailed,
+    CircuitOpen,
+    InvalidTraceId,
+    InvalidQuad,
+    QuadNotFound,
+    TooManyResults,
+    ResultRejected,
+    PartialResult,
+    CursorRepeated,
+    PrefixTooLarge,
     QueryNotFinalized,
     QueryCompilationFailed,
+    RootNameMismatch,
     ExpectationNotSupported,
+    ExpectationMismatch,
     VagueExpectation
 };
 
@@ -30,11 +49,41 @@ pub enum Error {
     MalformedRequest(HttpError, String),
     RequestIoFailed(IoError, Vec<u8>),
     RequestFailed(HttpError, Vec<u8>),
+    /// Cayley didn't respond in time, contains the timeout set with `Graph::with_timeout`
+    RequestTimedOut(Duration),
+    /// Cayley responded with a status other than `2xx`, contains the status and the response body
+    HttpStatus(StatusCode, String),
     DecodingFailed(DecoderError, String),
+    /// Cayley failed to perform a query, contains its explanation, i.e. a JavaScript error
+    ServerError(String),
     ResponseParseFailed,
+    ResponseTooLarge(u64),
+    OutputFailed(IoError),
+    CircuitOpen,
+    /// A trace id can't be sent in a header, i.e. it has line breaks, contains the id
+    InvalidTraceId(String),
+    /// Contains the name of the empty part: `subject`, `predicate`, `object` or `label`
+    InvalidQuad(String),
+    /// A quad to delete doesn't exist, contains Cayley's explanation
+    QuadNotFound(String),
+    /// A single result was expected, contains the number of results received
+    TooManyResults(usize),
+    /// Contains the explanation a result validator gave
+    ResultRejected(String),
+    /// Cayley returned an error, but also the nodes it found before it
+    PartialResult { message: String, nodes: Nodes },
+    /// Cayley returned a cursor it had already returned for this query, contains the cursor
+    CursorRepeated(String),
+    /// Contains the size of the rejected query prefix, in bytes
+    PrefixTooLarge(usize),
     QueryNotFinalized,
-    QueryCompilationFailed,
+    /// If the query string was checked and found malformed, contains the position of a problem
+    QueryCompilationFailed(Option<usize>),
+    /// A query is compiled for a root name other than the one of the Graph, contains the one of the Graph
+    RootNameMismatch(String),
     ExpectationNotSupported(Expectation),
+    /// The Final of a query is not the one this method handles, contains what the query expects
+    ExpectationMismatch(Expectation),
     VagueExpectation
 }
 
@@ -42,7 +91,16 @@ pub type Result<T> = ::std::result::Result<T, Error>;
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> FormatResult {
-        f.write_str(self.description())
+        match *self {
+            InvalidUrl(_, ref url) | MalformedRequest(_, ref url) =>
+                write!(f, "{}, Url: {}", self.description(), url),
+            RequestIoFailed(_, ref query) | RequestFailed(_, ref query) =>
+                write!(f, "{}, Query: {}", self.description(), String::from_utf8_lossy(query)),
+            DecodingFailed(_, ref source) => write!(f, "{}, Source: {:.200}", self.description(), source),
+            ServerError(ref explanation) => write!(f, "{}: {}", self.description(), explanation),
+            HttpStatus(ref status, ref body) => write!(f, "{} with {}: {:.200}", self.description(), status, body),
+            _ => f.write_str(self.description())
+        }
     }
 }
 
@@ -50,19 +108,30 @@ impl StdError for Error {
     fn description(&self) -> &str {
         match *self {
             InvalidUrl(_, _) => "Invalid Url",
-            //InvalidUrl(_, ref url) => format!("Invalid Url: {}", &url).as_str(),
             MalformedRequest(_, _) => "Malformed Request",
-            //MalformedRequest(_, ref url) => format!("Malformed Request, Url: {}", url).as_str(),
             RequestIoFailed(_, _) => "Request I/O Failed",
-            //RequestIoFailed(_, path) => format!("Request I/O Failed, Path: {}", String::from_utf8(path).unwrap()).as_str(),
             RequestFailed(_, _) => "Request failed",
-            //RequestFailed(_, path) => format!("Request Failed, Path: {}", String::from_utf8(path).unwrap()).as_str(),
+            RequestTimedOut(_) => "Request timed out",
+            HttpStatus(..) => "Cayley rejected the request",
             DecodingFailed(_, _) => "Decoding failed",
-            //DecodingFailed(_, ref src) => format!("Decoding failed, Source: {:.200}", src).as_str(),
+            ServerError(_) => "Cayley returned an error",
             ResponseParseFailed => "Response parsing failed",
+            ResponseTooLarge(_) => "Response exceeded the maximum allowed size",
+            OutputFailed(_) => "Writing the output failed",
+            CircuitOpen => "Cayley failed too many times in a row, requests are paused for a while",
+            InvalidTraceId(_) => "Trace id may contain only printable ASCII characters",
+            InvalidQuad(_) => "Quad has an empty part",
+            QuadNotFound(_) => "Quad to delete does not exist",
+            TooManyResults(_) => "Expected a single result, but received more",
+            ResultRejected(_) => "Result was rejected by a validator",
+            PartialResult { .. } => "Cayley returned an error, but also a part of the result",
+            CursorRepeated(_) => "Cayley returned the same cursor again, so the pages would never end",
+            PrefixTooLarge(_) => "Query prefix exceeded the maximum allowed size",
             QueryNotFinalized => "Query is not finalized",
-            QueryCompilationFailed => "Query compilation failed",
-            ExpectationNotSupported(_) => "Finals like ToValue(), ToArray(), TagValue(), TagArray() are currently not supported in Cayley DB for HTTP queries and they return nothing.",
+            QueryCompilationFailed(_) => "Query compilation failed",
+            RootNameMismatch(_) => "Query is compiled for a root name other than the one set with Graph::with_root_name",
+            ExpectationNotSupported(_) => "Finals like ToValue(), ToArray(), TagValue(), TagArray() are sent only to Cayley probed to support them with Graph::with_capability_probe, and with Graph::find_one, Graph::find_names, Graph::find_tag or Graph::find_tags",
+            ExpectationMismatch(_) => "Query Final is not the one this method handles",
             VagueExpectation => "Driver has no knowledge of what to expect in response from Cayley"
         }
     }
@@ -74,6 +143,7 @@ impl StdError for Error {
             RequestIoFailed(ref ioerr, _) => Some(ioerr),
             RequestFailed(ref herr, _) => Some(herr),
             DecodingFailed(ref derr, _) => Some(derr),
+            OutputFailed(ref ioerr) => Some(ioerr),
             _ => None,
         }
     }
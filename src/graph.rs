@@ -2,28 +2,53 @@ extern crate rustc_serialize;
 
 use std::str;
 use std::io::{Write, Read};
+use std::io::Error as IoError;
+use std::io::ErrorKind::{InvalidInput, WouldBlock, TimedOut};
 
-use self::rustc_serialize::{Decoder, Decodable};
+use self::rustc_serialize::{Decoder, Decodable, Encoder, Encodable};
 use self::rustc_serialize::json::decode as json_decode;
+use self::rustc_serialize::json::encode as json_encode;
+use self::rustc_serialize::json::{Json, DecoderError, Parser, JsonEvent, StackElement};
+use self::rustc_serialize::json::Decoder as JsonDecoder;
 
 use std::collections::HashMap;
+use std::ops::Deref;
+use std::fmt::{Display, Formatter};
+use std::fmt::Result as FormatResult;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use std::thread;
 
 use hyper::Url;
-use hyper::client::Request;
+use hyper::Client;
+use hyper::Error as HttpError;
 use hyper::method::Method;
-use hyper::header::ContentLength;
+use hyper::header::{Headers, ContentLength, UserAgent};
 
-use path::CompiledQuery;
+use path::{CompiledQuery, CompiledRoute, CompiledReuse, Trail, Vertex};
+use path::{validate_query, merge_prefixes};
+use path::Traversal::{As, OutP, OutT, InP, Filter, Unique};
+use path::Comparison;
+use path::Final::All;
+use selector::NodeSelector;
+use selector::TagSelector::Tag;
+use selector::PredicateSelector::Predicate;
 
 use path::Expectation;
-use path::Expectation::{ SingleNode, SingleTag,
-                         NameSequence, TagSequence };
+use quad::Quad;
+use path::Expectation::{ SingleNode, SingleTag, NodeSequence,
+                         NameSequence, TagSequence, CountValue, EmittedSequence };
 
 use url::ParseError;
 
 use error::Result as GraphResult;
 use error::Error::{ InvalidUrl, MalformedRequest, RequestIoFailed, RequestFailed,
-                    DecodingFailed, ResponseParseFailed, ExpectationNotSupported };
+                    DecodingFailed, ServerError, ResponseParseFailed, ResponseTooLarge,
+                    OutputFailed, CircuitOpen, TooManyResults, ResultRejected, PartialResult, PrefixTooLarge, QuadNotFound,
+                    RequestTimedOut, HttpStatus, ExpectationMismatch, CursorRepeated,
+                    QueryCompilationFailed, ExpectationNotSupported, InvalidTraceId, RootNameMismatch };
+use error::Error as GraphError;
 
 /// Provides access to currently running Cayley database, among with
 /// an ability to run queries there, and to write there your data
@@ -32,15 +57,148 @@ use error::Error::{ InvalidUrl, MalformedRequest, RequestIoFailed, RequestFailed
 ///
 /// * Use `Graph::default()` to connect to `localhost:64210`.
 /// * Use `Graph::new(host, port, api_version)` to specify the location of database manually.
+/// * Use `Graph::default_to(host, port)` to do the same with the default API version.
+/// * Use `Graph::new_connected(host, port, api_version)` to also check the database responds.
+/// * Use `Graph::new_secure(host, port, api_version)` to connect with HTTPS.
 ///
 /// * Use `Graph::find(<Query>)` to find anything using [Query](../path/trait.Query.html) trait implementor
 /// (`Query`, for example, is implemented by [Vertex](../path/struct.Vertex.html)), which in its turn
 /// is similar to [Gremlin API](https://github.com/google/cayley/blob/master/docs/GremlinAPI.md).
+/// * Use `Graph::find_rows(<Query>)` to read the `id` and the tags of every node found without knowing their keys.
+/// * Use `Graph::find_one(<Query>)` or `Graph::find_scalar(<Query>, <key>)` when a single node is expected.
+/// * Use `Graph::find_ordered(<Query>)` to keep the keys of every node in the order Cayley sent them.
+/// * Use `Graph::find_names(<Query>)` to get only the ids of the nodes with `ToArray()` Final.
+/// * Use `Graph::find_tags(<Query>)` or `Graph::find_tag(<Query>)` to get only the values of the tags
+/// with `TagArray()` or `TagValue()` Final.
+/// * Use `Graph::find_emitted(<Query>)` to get the values emitted with `ForEach(<body>)` Final.
+/// * Use `Graph::count(<Query>)` to count the nodes with `Count()` Final.
+/// * Use `Graph::shape(<Query>)` to get the plan Cayley would execute a query with.
+/// * Use `Graph::find_paged(<Route>, <page size>)` to iterate over the nodes page by page.
+/// * Use `Graph::find_many(<Queries>, <BatchMode>)` to perform several queries and get a result for each.
+/// * Use `Graph::exec_many(<Queries>)` to perform several queries with a single request.
+/// * Use `Graph::find_jsonl(<Query>, <Write>)` to write found nodes as JSON, one node per line.
+/// * Use `Graph::find_capped(<Query>, <cap>)` to ask Cayley to return no more than `cap` nodes.
+/// * Use `Graph::find_traced(<Query>, <trace id>)` to mark the logs and the request for this query with
+/// an id of your choice.
+/// * Use `Graph::find_with_stats(<Query>)` to get nodes along with the measurements of the query.
+/// * Use `Graph::find_by_degree(<Route>, <predicate>, <min>)` to find nodes having at least `min` outgoing
+/// edges with given predicate.
+/// * Use `Graph::find_nodes(<ids>)` to look up several nodes by id with one query.
+/// * Use `Graph::find_ci(<predicate>, <value>)` to find nodes by a predicate value ignoring its case.
+/// * Use `Graph::relationships(<node>)` to get `(predicate, target)` pairs for every outgoing edge of a node.
+/// * Use `Graph::find_typed(<Query>)` or `Graph::find_as(<Query>)` to decode found items,
+/// with their tags, into your own type.
+/// * Use `Graph::find_multitags(<Query>)` to get all the values every tag had for every node.
+/// * Use `Graph::find_mql(<JSON>)` to find objects with an MQL query instead of Gremlin one.
+/// * Use `Graph::morphism(<name>, <fn>)` to compile a [Morphism](../path/struct.Morphism.html) once
+/// and reuse it for every query.
 /// * Use `Graph::find_by(<String>)` to find anything using [Gremlin API](https://github.com/google/cayley/blob/master/docs/GremlinAPI.md) API
 /// from a prepared string. A raw, but not so beautiful, way to execute query.
-/// * Use `Graph::save(<Path>)` to save a [Morphism](../path/struct.Morphism.html).
+/// * Use `Graph::exec_raw(<String>)` to get the response body to a raw query as Cayley sent it.
+/// * Use `Graph::to_gremlin(<Query>)` to see the query exactly as `find` would send it.
+/// * Use `Graph::save(<Morphism>)` to declare a [Morphism](../path/struct.Morphism.html) before
+/// every query sent, so queries may follow it by its name.
+/// * Use `Graph::write(<quads>)` to put new links into Cayley and `Graph::delete(<quads>)` to remove them.
+///
+/// * Use `Graph::ping()` to check if Cayley responds.
+///
+/// * Use `Graph::with_timeout(<duration>)` to stop waiting for Cayley after some time.
+/// * Use `Graph::with_max_response_bytes(<n>)` to refuse responses larger than `n` bytes.
+/// * Use `Graph::with_max_prefix_bytes(<n>)` to reject queries with too many Morphisms
+/// declared before them.
+/// * Use `Graph::with_root_name(<name>)` if Cayley binds the graph to some name other than `g`.
+/// * Use `Graph::with_user_agent(<app>)` to tell Cayley which application sends the queries.
+/// * Use `Graph::with_circuit_breaker(<failures>, <cooldown>)` to stop sending requests for a while
+/// after Cayley failed to respond several times in a row.
+/// * Use `Graph::with_retries(<n>)` to send a failed query again, up to `n` times.
+/// * Use `Graph::with_method(<Method>)` to send queries with `GET` instead of `POST`.
+/// * Use `Graph::with_content_type(<type>)` to change the `Content-Type` queries are sent with.
+/// * Use `Graph::with_value_transform(<key>, <fn>)` to convert every value of some key in found nodes.
+/// * Use `Graph::with_numeric_fields(<keys>)` to get the values of these keys as numbers
+/// with `Graph::find_numeric`.
+/// * Use `Graph::with_result_validator(<fn>)` to check every result before it is returned.
+/// * Use `Graph::with_capability_probe()` to ask Cayley if it supports Finals like `ToValue()`
+/// before rejecting them.
+/// * Use `Graph::with_query_recording()` to see the exact query sent last with `Graph::last_query()`.
+///
+/// Every request blocks the calling thread until Cayley responds, there are no async
+/// variants of `find` and `exec` yet. In async code, call them where blocking is allowed,
+/// i.e. inside `tokio::task::spawn_blocking`, with the Graph created in the same closure,
+/// since a Graph can't be sent to another thread.
 pub struct Graph {
-    url: String
+    // a single client for all the requests, so the connections to Cayley are reused
+    client: Client,
+    url: Url,
+    mql_url: Url,
+    shape_url: Url,
+    write_url: Url,
+    delete_url: Url,
+    user_agent: String,
+    timeout: Option<Duration>,
+    max_response_bytes: Option<u64>,
+    max_prefix_bytes: Option<usize>,
+    root_name: String,
+    breaker: Option<CircuitBreaker>,
+    retries: u32,
+    method: Method,
+    content_type: String,
+    transforms: HashMap<String, Box<Fn(&str) -> String>>,
+    numeric_fields: Vec<String>,
+    validators: Vec<Box<Fn(&Nodes) -> Result<(), String>>>,
+    morphisms: RefCell<HashMap<String, Rc<CompiledReuse>>>,
+    // declared before every query, see `Graph::save`
+    saved: Vec<CompiledReuse>,
+    // `None` if queries are not recorded
+    last_query: Option<RefCell<Option<String>>>,
+    // `None` if capabilities are not probed, Finals are just rejected then
+    capabilities: Option<RefCell<HashMap<Expectation, bool>>>
+}
+
+/// The `Content-Type` queries are sent with, unless changed with `Graph::with_content_type`
+pub const DEFAULT_CONTENT_TYPE: &'static str = "text/plain; charset=utf-8";
+
+// the name Cayley binds the graph to in queries, unless changed with `Graph::with_root_name`
+pub use path::DEFAULT_ROOT_NAME;
+
+/// The pause before the first retry of a failed query, doubled for every next one,
+/// see `Graph::with_retries`
+pub const RETRY_BACKOFF_MS: u64 = 100;
+
+/// Queries sent with `GET` producing longer URLs are sent with `POST` instead
+pub const MAX_GET_URL_LENGTH: usize = 2048;
+
+/// The state of a Graph's circuit breaker, see `Graph::with_circuit_breaker`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CircuitState {
+    /// Requests are sent, the value is the number of transport failures in a row
+    Closed(u32),
+    /// Requests fail with `CircuitOpen` without being sent, since the given moment
+    Open(Instant),
+    /// The cooldown has passed and a single request is let through to probe Cayley
+    HalfOpen
+}
+
+// where and how a request is sent: queries are sent to the query endpoint as configured,
+// quads are always sent to the write or delete endpoint with `POST`, as JSON
+struct Endpoint<'e> {
+    url: &'e Url,
+    method: &'e Method,
+    content_type: &'e str
+}
+
+const JSON_CONTENT_TYPE: &'static str = "application/json";
+
+// the key of an object emitted after the nodes of every query sent with `Graph::exec_many`
+const BATCH_END_KEY: &'static str = "__batch_end";
+
+// the tag `Graph::find_by_degree` marks the start of every edge with, so it doesn't
+// overwrite the tags set in the route
+const DEGREE_TAG: &'static str = "__degree_source";
+
+struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Cell<CircuitState>
 }
 
 /// A wrapper for a single item Cayley returned in response for a query
@@ -55,7 +213,127 @@ pub struct Graph {
     SingleTag(String) // Query.TagValue()
 } */
 
-pub struct Nodes(pub Vec<HashMap<String, String>>);
+#[derive(Debug)]
+pub struct Nodes(pub Vec<Node>);
+
+// a single response from Cayley: the nodes and, if Cayley has more
+// nodes to send, a cursor to request the next portion with
+struct Page {
+    nodes: Nodes,
+    cursor: Option<String>
+}
+
+/// A single node Cayley returned, a wrapper for `HashMap<String, String>`.
+/// Dereferences to the map itself, so `node["id".to_string()]` still works.
+#[derive(Debug)]
+pub struct Node(pub HashMap<String, String>);
+
+/// A single result row, the `id` of a node along with the tags set for it with `As`,
+/// `Save` & others, and the label, if Cayley has put it there, see `Graph::find_rows`.
+/// Dereferences to the map itself, like `Node`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultRow(pub HashMap<String, String>);
+
+/// Same as `Nodes`, but every node keeps its keys in the order Cayley sent them,
+/// see `Graph::find_ordered`
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderedNodes(pub Vec<OrderedNode>);
+
+/// A single node with its keys in the order Cayley sent them, as `(key, value)` pairs
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderedNode(pub Vec<(String, String)>);
+
+impl OrderedNode {
+
+    /// Get a value by its key, if there is one
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.iter().find(|&&(ref k, _)| k == key).map(|&(_, ref value)| value.as_str())
+    }
+
+}
+
+/// A result item of any shape, for queries where Cayley returns not only flat
+/// nodes (like grouping or shape queries), see `Graph::find_nested`
+#[derive(Debug, PartialEq)]
+pub enum Value {
+    /// An object with only string values, just a usual node
+    Node(HashMap<String, String>),
+    /// An object containing other objects or arrays
+    Group(HashMap<String, Value>),
+    List(Vec<Value>),
+    Text(String),
+    Null
+}
+
+/// The plan of a query, see `Graph::shape`: the iterators Cayley would use
+/// to execute it and the links between them
+#[derive(Debug, Clone, PartialEq, RustcDecodable)]
+pub struct Shape {
+    pub nodes: Vec<ShapeNode>,
+    pub links: Vec<ShapeLink>
+}
+
+/// A node of a query `Shape`, either a set of fixed `values` or the nodes a link
+/// leads to, which may be tagged
+#[derive(Debug, Clone, PartialEq, RustcDecodable)]
+pub struct ShapeNode {
+    pub id: i64,
+    pub tags: Option<Vec<String>>,
+    pub values: Option<Vec<String>>,
+    pub is_link_node: bool,
+    pub is_fixed: bool
+}
+
+/// A link of a query `Shape`, from the `source` node to the `target` node through
+/// the `link_node` (the node for predicates), all referred by their ids
+#[derive(Debug, Clone, PartialEq, RustcDecodable)]
+pub struct ShapeLink {
+    pub source: i64,
+    pub target: i64,
+    pub link_node: i64
+}
+
+/// The pages of nodes found with `Graph::find_paged`, every page is requested
+/// only when it is iterated to
+pub struct Pages<'g> {
+    graph: &'g Graph,
+    route: CompiledRoute,
+    page_size: usize,
+    skip: usize,
+    done: bool
+}
+
+/// A value of a node found with `Graph::find_numeric`
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeValue {
+    /// A value under one of the keys set with `Graph::with_numeric_fields`, parsed as a number
+    Num(f64),
+    /// Any other value, or a value which failed to parse as a number
+    Str(String)
+}
+
+/// How `Graph::find_many` handles the failed queries
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BatchMode {
+    /// Run all the queries, whichever fail
+    CollectAll,
+    /// Stop at the first failed query, the queries after it are not sent
+    AbortOnError
+}
+
+/// What it took to perform a query, see `Graph::find_with_stats`. Cayley
+/// itself reports no statistics, so these are measured by the driver.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryStats {
+    /// The time from the start of the first request to the decoding of the last response
+    pub duration: Duration,
+    /// The size of the query sent, summed for all requests
+    pub request_bytes: usize,
+    /// The size of the response bodies received, summed for all requests
+    pub response_bytes: usize,
+    /// The number of requests sent, more than one if the result was paged
+    pub requests: usize
+}
 
 /// Cayley API Version, planned to default to the latest, if it will ever change
 pub enum APIVersion { V1, DefaultVersion }
@@ -73,12 +351,369 @@ impl Graph {
 
     /// Create a Graph which connects to the host you specified manually
     pub fn new(host: &str, port: i32, version: APIVersion) -> GraphResult<Graph> {
+        Graph::at("http", host, port, version)
+    }
+
+    // ---------------------------------- default_to ---------------------------
+
+    /// Same as `new`, but with the `DefaultVersion` of API
+    pub fn default_to(host: &str, port: i32) -> GraphResult<Graph> {
+        Graph::new(host, port, APIVersion::DefaultVersion)
+    }
+
+    // ---------------------------------- new_secure ---------------------------
+
+    /// Same as `new`, but connects with HTTPS, i.e. to Cayley deployed behind
+    /// a TLS terminator
+    pub fn new_secure(host: &str, port: i32, version: APIVersion) -> GraphResult<Graph> {
+        Graph::at("https", host, port, version)
+    }
+
+    fn at(scheme: &str, host: &str, port: i32, version: APIVersion) -> GraphResult<Graph> {
         let version_str = match version {
             APIVersion::V1 | APIVersion::DefaultVersion => "v1" /* FIXME: APIVersion:: shouldn't be required */
         };
-        let url = format!("http://{host}:{port}/api/{version}/query/gremlin",
-                          host = host, port = port, version = version_str);
-        Ok(Graph{ url: url })
+        let api_url = format!("{scheme}://{host}:{port}/api/{version}",
+                              scheme = scheme, host = host, port = port, version = version_str);
+        let (url, mql_url, shape_url) = match (parse_url(format!("{}/query/gremlin", api_url)),
+                                               parse_url(format!("{}/query/mql", api_url)),
+                                               parse_url(format!("{}/shape/gremlin", api_url))) {
+            (Ok(url), Ok(mql_url), Ok(shape_url)) => (url, mql_url, shape_url),
+            (Err(error), _, _) | (_, Err(error), _) | (_, _, Err(error)) => return Err(error)
+        };
+        let (write_url, delete_url) = match (parse_url(format!("{}/write", api_url)),
+                                             parse_url(format!("{}/delete", api_url))) {
+            (Ok(write_url), Ok(delete_url)) => (write_url, delete_url),
+            (Err(error), _) | (_, Err(error)) => return Err(error)
+        };
+        Ok(Graph{ client: Client::new(),
+                  url: url,
+                  mql_url: mql_url,
+                  shape_url: shape_url,
+                  write_url: write_url,
+                  delete_url: delete_url,
+                  user_agent: format!("cayley-rust/{}", env!("CARGO_PKG_VERSION")),
+                  timeout: None,
+                  max_response_bytes: None,
+                  max_prefix_bytes: None,
+                  root_name: DEFAULT_ROOT_NAME.to_string(),
+                  breaker: None,
+                  retries: 0,
+                  method: Method::Post,
+                  content_type: DEFAULT_CONTENT_TYPE.to_string(),
+                  transforms: HashMap::new(),
+                  numeric_fields: Vec::new(),
+                  validators: Vec::new(),
+                  morphisms: RefCell::new(HashMap::new()),
+                  saved: Vec::new(),
+                  last_query: None,
+                  capabilities: None })
+    }
+
+    // ---------------------------------- new_connected ------------------------
+
+    /// Same as `new`, but a trivial query is sent to Cayley immediately, so
+    /// if it is unreachable, you get the error right here and not on the first query
+    pub fn new_connected(host: &str, port: i32, version: APIVersion) -> GraphResult<Graph> {
+        match Graph::new(host, port, version) {
+            Ok(graph) => match graph.ping() {
+                Ok(_) => Ok(graph),
+                Err(error) => Err(error)
+            },
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- ping ---------------------------------
+
+    /// Send a query which finds nothing, just to see if Cayley responds, i.e. once
+    /// at startup to fail fast. `Graph::new` sends nothing, so that's the way to
+    /// check a Graph created with it:
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// graph.ping().unwrap();
+    /// ```
+    pub fn ping(&self) -> GraphResult<()> {
+        let probe = match self.full_query(String::new(), &format!("{}.V().Limit(0).All()", self.root_name)) {
+            Ok(probe) => probe, Err(error) => return Err(error)
+        };
+        // the response is not decoded, so the result validators never see it
+        match self.perform_request(probe.into_bytes(), &[], None) {
+            Ok(body) => match server_error(String::from_utf8_lossy(body.as_slice()).as_ref()) {
+                Some(error) => Err(error),
+                None => Ok(())
+            },
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- with_timeout -------------------------
+
+    /// Stop waiting for Cayley to receive a request or to send a response after
+    /// `timeout`, the request fails with `RequestTimedOut` then, so it can be told
+    /// apart from other failures, i.e. to be retried
+    pub fn with_timeout(mut self, timeout: Duration) -> Graph {
+        self.client.set_read_timeout(Some(timeout));
+        self.client.set_write_timeout(Some(timeout));
+        self.timeout = Some(timeout);
+        self
+    }
+
+    // ---------------------------------- with_max_response_bytes --------------
+
+    /// Limit the size of a response body this Graph agrees to read, the request
+    /// fails with `ResponseTooLarge` as soon as Cayley sends more than `limit` bytes,
+    /// so the rest of the body is never buffered
+    pub fn with_max_response_bytes(mut self, limit: u64) -> Graph {
+        self.max_response_bytes = Some(limit);
+        self
+    }
+
+    // ---------------------------------- with_max_prefix_bytes ----------------
+
+    /// Reject queries which prefix, the `var` declarations of all the Morphisms
+    /// they use, is longer than `limit` bytes, with `PrefixTooLarge` error and
+    /// without sending them, i.e. to catch the Morphisms composed too deeply
+    pub fn with_max_prefix_bytes(mut self, limit: usize) -> Graph {
+        self.max_prefix_bytes = Some(limit);
+        self
+    }
+
+    fn full_query(&self, prefix: String, value: &str) -> GraphResult<String> {
+        let prefix = self.saved.iter().map(|saved| saved.to_gremlin()).collect::<Vec<String>>().concat() + &prefix;
+        match self.max_prefix_bytes {
+            Some(limit) if prefix.len() > limit => return Err(PrefixTooLarge(prefix.len())),
+            _ => {}
+        };
+        // a query compiled for another root would reach a graph Cayley doesn't bind, if any
+        if !value.starts_with(&format!("{}.", self.root_name)) {
+            return Err(RootNameMismatch(self.root_name.clone()))
+        }
+        Ok(prefix + value)
+    }
+
+    // ---------------------------------- with_root_name -----------------------
+
+    /// Queries and Morphisms are compiled as `g.V(..)` and `g.M()`, with `g` being
+    /// the name Cayley binds the graph to. If your Cayley binds it to some other
+    /// name (i.e. `graph`), set it here, and compile the queries for it with
+    /// `Vertex::compile_query_at` and others, like `graph.V("foo").All()`; the queries
+    /// the Graph makes itself, i.e. with `ping` or `find_nodes`, are compiled for it then.
+    /// Queries compiled for another name fail with `RootNameMismatch` without sending
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap().with_root_name("graph");
+    /// graph.find(Vertex::compile_query_at("graph", Node("foo"), vec![].into_boxed_slice(), All).unwrap()).unwrap();
+    /// ```
+    pub fn with_root_name(mut self, name: &str) -> Graph {
+        self.root_name = name.to_string();
+        self
+    }
+
+    // ---------------------------------- with_user_agent ----------------------
+
+    /// Append an identifier of your application to the `User-Agent` header sent
+    /// with every request, i.e. `cayley-rust/0.0.2 my-app/1.0`
+    pub fn with_user_agent(mut self, app: &str) -> Graph {
+        self.user_agent = format!("{} {}", self.user_agent, app);
+        self
+    }
+
+    // ---------------------------------- with_retries ------------------------
+
+    /// When a query fails to be sent or received (Cayley restarts or the network
+    /// fails for a moment), send it again, up to `retries` times, pausing for
+    /// `RETRY_BACKOFF_MS` before the first retry and twice longer before every next one.
+    /// If all the attempts fail, the error of the last one is returned. Decoding errors
+    /// and errors from Cayley are never retried, they would just happen again;
+    /// neither are writes and deletes, which may be not safe to repeat.
+    pub fn with_retries(mut self, retries: u32) -> Graph {
+        self.retries = retries;
+        self
+    }
+
+    // ---------------------------------- with_circuit_breaker ----------------
+
+    /// After `threshold` transport failures in a row (Cayley is down or
+    /// unreachable), make every request fail with `CircuitOpen` immediately,
+    /// without actually sending it, until `cooldown` passes. Then the next request
+    /// is sent as a probe: if it succeeds, requests go as usual again, if it
+    /// fails, they're paused for another `cooldown`.
+    pub fn with_circuit_breaker(mut self, threshold: u32, cooldown: Duration) -> Graph {
+        self.breaker = Some(CircuitBreaker { threshold: threshold,
+                                             cooldown: cooldown,
+                                             state: Cell::new(CircuitState::Closed(0)) });
+        self
+    }
+
+    /// The current state of the circuit breaker, `None` if it wasn't configured
+    pub fn circuit_state(&self) -> Option<CircuitState> {
+        self.breaker.as_ref().map(|breaker| breaker.state.get())
+    }
+
+    // ---------------------------------- with_method --------------------------
+
+    /// Choose the HTTP method to send queries with: `Method::Post` (the default)
+    /// sends a query in the body, `Method::Get` sends it in URL as `?query=...`,
+    /// so the responses may be cached by HTTP proxies. If the URL with a query
+    /// would be longer than `MAX_GET_URL_LENGTH`, this query is sent with `POST` anyway.
+    pub fn with_method(mut self, method: Method) -> Graph {
+        self.method = method;
+        self
+    }
+
+    // ---------------------------------- with_content_type --------------------
+
+    /// Change the `Content-Type` header queries are sent with, from `DEFAULT_CONTENT_TYPE`
+    /// to, say, `application/javascript`, if a proxy in front of Cayley requires it
+    pub fn with_content_type(mut self, content_type: &str) -> Graph {
+        self.content_type = content_type.to_string();
+        self
+    }
+
+    // ---------------------------------- with_value_transform -----------------
+
+    /// Convert the value under `key` in every found node with `transform`, as the
+    /// nodes are decoded, i.e. to decode the blobs your application stores encoded:
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap()
+    ///                   .with_value_transform("thumbnail", |encoded| decode_base64(encoded));
+    /// ```
+    ///
+    /// Other keys are left as they are. Setting a transform for the same key twice
+    /// replaces the previous one.
+    pub fn with_value_transform<F>(mut self, key: &str, transform: F) -> Graph
+                                   where F: Fn(&str) -> String + 'static {
+        self.transforms.insert(key.to_string(), Box::new(transform));
+        self
+    }
+
+    fn transform_values(&self, nodes: &mut Vec<Node>) {
+        if self.transforms.is_empty() { return }
+        for node in nodes.iter_mut() {
+            for (key, transform) in self.transforms.iter() {
+                match node.0.get_mut(key) {
+                    Some(value) => *value = transform(value.as_str()),
+                    None => {}
+                }
+            }
+        }
+    }
+
+    // ---------------------------------- with_numeric_fields ------------------
+
+    /// Treat the values under these keys as numbers: nodes found with
+    /// `Graph::find_numeric` have them already parsed into `NodeValue::Num`,
+    /// so there's no need to `.parse()` them every time:
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap().with_numeric_fields(&["year", "rating"]);
+    /// ```
+    pub fn with_numeric_fields(mut self, keys: &[&str]) -> Graph {
+        self.numeric_fields = keys.iter().map(|key| key.to_string()).collect();
+        self
+    }
+
+    // ---------------------------------- with_result_validator ----------------
+
+    /// Check the nodes found by every query with `validator` before returning them:
+    /// if it returns an `Err` with an explanation, the query fails with `ResultRejected`
+    /// containing this explanation. Several validators may be added, they are
+    /// called in the order they were added.
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap().with_result_validator(|&Nodes(ref nodes)| {
+    ///     if nodes.is_empty() { Err("nothing was found".to_string()) } else { Ok(()) }
+    /// });
+    /// ```
+    pub fn with_result_validator<F>(mut self, validator: F) -> Graph
+                                    where F: Fn(&Nodes) -> Result<(), String> + 'static {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    // ---------------------------------- with_capability_probe ---------------
+
+    /// By default, queries with Finals like `ToValue()`, `ToArray()`, `TagValue()`,
+    /// `TagArray()` are rejected with `ExpectationNotSupported` without sending,
+    /// by every method, since Cayley HTTP API returns nothing for them. With this option, the first
+    /// time such a Final is met, a probe query (i.e. `g.V().ToValue()`) is sent
+    /// to check if this Cayley performs it without an error, and the answer is remembered:
+    /// queries with this Final are sent to capable Cayley and rejected otherwise.
+    pub fn with_capability_probe(mut self) -> Graph {
+        self.capabilities = Some(RefCell::new(HashMap::new()));
+        self
+    }
+
+    // ---------------------------------- with_query_recording ---------------
+
+    /// Remember the exact query sent to Cayley the last time, by any method, after
+    /// all the rewrites made by the driver (i.e. with the saved Morphisms declared
+    /// before it), see `Graph::last_query`
+    pub fn with_query_recording(mut self) -> Graph {
+        self.last_query = Some(RefCell::new(None));
+        self
+    }
+
+    /// The query sent to Cayley the last time, `None` if nothing was sent yet
+    /// or if this Graph was not created `with_query_recording()`
+    pub fn last_query(&self) -> Option<String> {
+        match self.last_query {
+            Some(ref last_query) => last_query.borrow().clone(),
+            None => None
+        }
+    }
+
+    // the same check for every method sending a query, so they all agree on what is supported
+    fn check_supported(&self, expectation: Expectation) -> GraphResult<()> {
+        match self.supports(expectation) {
+            true => Ok(()),
+            false => Err(ExpectationNotSupported(expectation))
+        }
+    }
+
+    fn supports(&self, expectation: Expectation) -> bool {
+        let probe = match expectation {
+            Expectation::Unknown | NodeSequence | CountValue | EmittedSequence => return true,
+            SingleNode => ".V().ToValue()",
+            NameSequence => ".V().ToArray()",
+            TagSequence => ".V().TagArray()",
+            SingleTag => ".V().TagValue()"
+        };
+        let probe = self.root_name.clone() + probe;
+        match self.capabilities {
+            None => false,
+            Some(ref capabilities) => {
+                match capabilities.borrow().get(&expectation) {
+                    Some(&supported) => return supported,
+                    None => {}
+                };
+                let probe = match self.full_query(String::new(), &probe) {
+                    Ok(probe) => probe, Err(_) => return false
+                };
+                debug!("Probing {:?} support with {}", expectation, probe);
+                // the Final is supported if Cayley performs it without an error, even
+                // if there's nothing to return, i.e. in an empty database
+                let supported = match self.perform_request(probe.into_bytes(), &[], None) {
+                    Ok(body) => match str::from_utf8(body.as_slice()) {
+                        Ok(source) => match Json::from_str(source) {
+                            Ok(response) => match response.find("error") {
+                                None | Some(&Json::Null) => true,
+                                _ => false
+                            },
+                            Err(_) => false
+                        },
+                        Err(_) => false
+                    },
+                    // Cayley didn't answer, so it's not known yet
+                    Err(_) => return false
+                };
+                capabilities.borrow_mut().insert(expectation, supported);
+                supported
+            }
+        }
     }
 
     // ---------------------------------- find ---------------------------------
@@ -97,128 +732,1717 @@ impl Graph {
     /// graph.find(Vertex::start(Node("foo")).InP(Predicate("bar")).All()).unwrap();
     /// ```
     pub fn find(&self, query: CompiledQuery) -> GraphResult<Nodes> {
-        self.exec(query.prefix + &query.value, query.expectation)
+        match self.full_query(query.prefix, &query.value) {
+            Ok(full) => self.exec(full, query.expectation),
+            Err(error) => Err(error)
+        }
     }
 
-    // ---------------------------------- exec ---------------------------------
+    // ---------------------------------- find_rows ----------------------------
 
-    /// Find nodes using raw pre-compiled query string and return them parsed
+    /// Same as `find`, but every node found is a `ResultRow`, with `id()`, `tag(name)`
+    /// and `tags()` to read it without knowing the keys Cayley has put the values under:
     ///
-    /// If you want to run just the pure stringified Gremlin queries, bypassing
-    /// the string concatenation performed with `path::` module members, this
-    /// method is for you.
+    /// ```ignore
+    /// let rows = graph.find_rows(vertex![ Node("B") -> As(Tag("source")) -> InP(Predicate("follows")) => All ]).unwrap();
+    /// for row in rows.iter() { println!("{:?} follows {:?}", row.id(), row.tag("source")); }
+    /// ```
+    pub fn find_rows(&self, query: CompiledQuery) -> GraphResult<Vec<ResultRow>> {
+        match self.find(query) {
+            Ok(Nodes(nodes)) => Ok(nodes.into_iter().map(ResultRow::from).collect()),
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_one -----------------------------
+
+    /// Find a single node with the Query implementation: `None` if nothing was
+    /// found, and `TooManyResults` error if more than one node was found.
+    /// For a Query ending with `ToValue()` Final, Cayley itself returns
+    /// a single node, so there's no such error for it, but it is sent only if
+    /// Cayley supports it, see `Graph::with_capability_probe`:
     ///
     /// ```ignore
-    /// use cayley::Graph;
     /// let graph = Graph::default().unwrap();
-    /// graph.exec("g.V(\"foo\").In(\"bar\").All()".to_string()).unwrap();
+    /// let casablanca = graph.find_one(vertex![ Node("Casablanca") -> InP(Predicate("name")) => ToValue ]).unwrap();
     /// ```
-    pub fn exec(&self, query: String, expectation: Expectation) -> GraphResult<Nodes> {
-        debug!("Executing query: {}", query);
-        match expectation {
-            SingleNode | NameSequence | TagSequence | SingleTag =>
-                Err(ExpectationNotSupported(expectation)),
-            _ => match self.perform_request(query.into_bytes()) {
-                Ok(body) => Graph::decode_traversal(body, expectation),
+    pub fn find_one(&self, query: CompiledQuery) -> GraphResult<Option<Node>> {
+        if query.expectation == SingleNode {
+            return match self.find_items(query, SingleNode) {
+                Ok(items) => match items.into_iter().next() {
+                    None | Some(Json::Null) => Ok(None),
+                    Some(item) => {
+                        let source = item.to_string();
+                        match decode_node(&mut JsonDecoder::new(item)) {
+                            Ok(node) => Ok(Some(node)),
+                            Err(error) => Err(DecodingFailed(error, source))
+                        }
+                    }
+                },
                 Err(error) => Err(error)
             }
         }
+        match self.find(query) {
+            Ok(Nodes(mut nodes)) => match nodes.len() {
+                0 => Ok(None),
+                1 => Ok(nodes.pop()),
+                count => Err(TooManyResults(count))
+            },
+            Err(error) => Err(error)
+        }
     }
 
-    fn perform_request(&self, body: Vec<u8>) -> GraphResult<Vec<u8>> {
-        let mut request = {
-            let url_str = self.url.as_str();
-            match Url::parse(url_str) {
-                Err(error) => return Err(InvalidUrl(error, url_str.to_string())),
-                Ok(parsed_url) => match Request::new(Method::Post, parsed_url) {
-                    Err(error) => return Err(MalformedRequest(error, url_str.to_string())),
-                    Ok(request) => request
-                }
-            }
-        };
-        request.headers_mut().set(ContentLength(body.len() as u64));
-        match request.start() {
-            Err(error) => return Err(RequestFailed(error, body)),
-            Ok(mut request) => match request.write(body.as_slice()) {
-                Err(error) => return Err(RequestIoFailed(error, body)),
-                Ok(_) => match request.send() {
-                    Err(error) => return Err(RequestFailed(error, body)),
-                    Ok(mut response) => {
-                        let mut response_body = Vec::new();
-                        match response.read_to_end(&mut response_body) {
-                            Err(error) => Err(RequestIoFailed(error, body)),
-                            Ok(_) => {
-                                debug!("Request to {} succeeded", self.url);
-                                Ok(response_body)
-                            }
-                        }
+    // ---------------------------------- find_scalar --------------------------
+
+    /// Find a single node with the Query implementation, like `find_one`, and
+    /// get the value under `key` from it: `None` if nothing was found or the
+    /// node has no such key
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let name = graph.find_scalar(vertex![ Node("/en/casablanca_1942") -> OutP(Predicate("name"))
+    ///                                       => All ], "id").unwrap();
+    /// ```
+    pub fn find_scalar(&self, query: CompiledQuery, key: &str) -> GraphResult<Option<String>> {
+        match self.find_one(query) {
+            Ok(found) => Ok(found.and_then(|node| node.get(key).map(|value| value.clone()))),
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_numeric -------------------------
+
+    /// Find nodes with the Query implementation, like `find`, but with the values
+    /// under the keys set with `Graph::with_numeric_fields` parsed as numbers
+    pub fn find_numeric(&self, query: CompiledQuery) -> GraphResult<Vec<HashMap<String, NodeValue>>> {
+        match self.find(query) {
+            Ok(Nodes(nodes)) => Ok(nodes.into_iter().map(|Node(node)| {
+                node.into_iter().map(|(key, value)| {
+                    let numeric = self.numeric_fields.contains(&key);
+                    let value = match value.parse::<f64>() {
+                        Ok(number) if numeric => NodeValue::Num(number),
+                        _ => NodeValue::Str(value)
+                    };
+                    (key, value)
+                }).collect()
+            }).collect()),
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_names ---------------------------
+
+    /// Find names with a Query ending with `ToArray()` Final, Cayley returns
+    /// just the ids of the nodes for it, instead of the node objects:
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let followers = graph.find_names(vertex![ Node("bar") -> InP(Predicate("follows")) => ToArray ]).unwrap();
+    /// ```
+    ///
+    /// Queries with other Finals fail with `ExpectationMismatch`, without sending.
+    /// `ToArray()` is sent only if Cayley supports it, see `Graph::with_capability_probe`.
+    pub fn find_names(&self, query: CompiledQuery) -> GraphResult<Vec<String>> {
+        match self.find_items(query, NameSequence) {
+            Ok(items) => decode_name_sequence(items),
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_tags ----------------------------
+
+    /// Find the tags with a Query ending with `TagArray()` Final, Cayley returns
+    /// only the values of the tags set in the query for it, a map per node.
+    /// `TagArray()` is sent only if Cayley supports it, see `Graph::with_capability_probe`
+    pub fn find_tags(&self, query: CompiledQuery) -> GraphResult<Vec<HashMap<String, String>>> {
+        match self.find_items(query, TagSequence) {
+            Ok(items) => {
+                let mut tags = Vec::with_capacity(items.len());
+                for item in items.into_iter() {
+                    match decode_tags(item) {
+                        Ok(found) => tags.push(found),
+                        Err(error) => return Err(error)
                     }
                 }
-            }
+                Ok(tags)
+            },
+            Err(error) => Err(error)
         }
+    }
+
+    // ---------------------------------- find_tag -----------------------------
 
+    /// Find the tags with a Query ending with `TagValue()` Final, like `find_tags`,
+    /// but for a single node: `None` if nothing was found
+    pub fn find_tag(&self, query: CompiledQuery) -> GraphResult<Option<HashMap<String, String>>> {
+        match self.find_items(query, SingleTag) {
+            Ok(items) => match items.into_iter().next() {
+                None | Some(Json::Null) => Ok(None),
+                Some(item) => decode_tags(item).map(Some)
+            },
+            Err(error) => Err(error)
+        }
     }
 
-    // extract JSON nodes from response
-    #[allow(unused_variables)]
-    fn decode_traversal(source: Vec<u8>, expectation: Expectation) -> GraphResult<Nodes> {
-        match str::from_utf8(source.as_slice()) {
-            Err(_) => Err(ResponseParseFailed),
-            Ok(traversal_json) => {
-                debug!("start decoding \n===\n{:.200}\n...\n===\n", traversal_json);
-                match json_decode(traversal_json) {
-                    Err(error) => Err(DecodingFailed(error, traversal_json.to_string())),
-                    Ok(nodes) => {
-                        debug!("Returned: {}", match nodes { Nodes(ref val) => val.len() });
-                        Ok(nodes)
-                    }
+    // ---------------------------------- count --------------------------------
+
+    /// Count the nodes with a Query ending with `Count()` Final, Cayley counts
+    /// them itself, so it's much cheaper than finding all of them:
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let follows = graph.count(vertex![ AnyNode -> OutP(Predicate("follows")) => Count ]).unwrap();
+    /// ```
+    pub fn count(&self, query: CompiledQuery) -> GraphResult<i64> {
+        match self.send_expecting(query, CountValue) {
+            Ok(source) => decode_count(source.as_str()),
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_ordered -------------------------
+
+    /// Find nodes with the Query implementation, like `find`, but keep the keys of
+    /// every node in the order Cayley sent them, i.e. for reproducible snapshots.
+    /// Nodes themselves are always returned in the order Cayley sent them, by `find` too.
+    /// The pages of a paged result are all requested, the same way `find` does,
+    /// but unlike `find`, neither value transforms nor result validators are applied.
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let OrderedNodes(nodes) = graph.find_ordered(vertex![ Node("C") -> As(Tag("source"))
+    ///                                                       -> Out(Predicate("follows"), Tag("target")) => All ]).unwrap();
+    /// ```
+    pub fn find_ordered(&self, query: CompiledQuery) -> GraphResult<OrderedNodes> {
+        if query.expectation != NodeSequence { return Err(ExpectationMismatch(query.expectation)) }
+        let query = match self.full_query(query.prefix, &query.value) {
+            Ok(query) => query, Err(error) => return Err(error)
+        };
+        debug!("Executing ordered query: {}", query);
+        let mut stats = QueryStats { duration: Duration::from_secs(0),
+                                     request_bytes: 0, response_bytes: 0, requests: 0 };
+        let mut found: Vec<OrderedNode> = Vec::new();
+        let received = self.follow_cursors(&query, None, None, &mut stats, |body| {
+            match String::from_utf8(body) {
+                Err(_) => Err(ResponseParseFailed),
+                Ok(source) => match decode_ordered_page(source.as_str()) {
+                    Ok((OrderedNodes(nodes), cursor)) => {
+                        found.extend(nodes.into_iter());
+                        Ok((found.len(), cursor))
+                    },
+                    Err(error) => Err(error)
                 }
             }
+        });
+        received.map(|_| OrderedNodes(found))
+    }
+
+    // ---------------------------------- find_emitted -------------------------
+
+    /// Find the values emitted with a Query ending with `ForEach(<body>)` Final,
+    /// where the body calls `g.Emit(..)` for the nodes, so the result may be shaped
+    /// on Cayley side:
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let names = graph.find_emitted(vertex![ Node("Casablanca")
+    ///                                         => ForEach("g.Emit({ film: d.id })") ]).unwrap();
+    /// ```
+    pub fn find_emitted(&self, query: CompiledQuery) -> GraphResult<Vec<Value>> {
+        match self.send_expecting(query, EmittedSequence) {
+            Ok(source) => Value::decode_result(source.as_str()),
+            Err(error) => Err(error)
         }
     }
 
-}
+    // ---------------------------------- find_mql -----------------------------
 
-impl Decodable for Nodes {
+    /// Find the objects matching an [MQL](https://github.com/google/cayley/blob/master/docs/MQL.md)
+    /// query-by-example, sent as is to the MQL endpoint of Cayley instead of Gremlin one.
+    /// The fields of every object are returned as strings, nested objects and arrays
+    /// are returned as their JSON
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let films = graph.find_mql("[{\"id\": null, \"name\": \"Casablanca\"}]").unwrap();
+    /// ```
+    pub fn find_mql(&self, query_json: &str) -> GraphResult<Vec<HashMap<String, String>>> {
+        debug!("Executing MQL query: {}", query_json);
+        match self.perform_request_to(&Endpoint { url: &self.mql_url, method: &Method::Post,
+                                                  content_type: JSON_CONTENT_TYPE },
+                                      query_json.as_bytes().to_vec(), &[], None) {
+            Ok(body) => match str::from_utf8(body.as_slice()) {
+                Err(_) => Err(ResponseParseFailed),
+                Ok(source) => match result_items(source) {
+                    Ok(items) => decode_mql_items(items),
+                    Err(error) => Err(error)
+                }
+            },
+            Err(error) => Err(error)
+        }
+    }
 
-    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, D::Error> {
-        decode_nodes(decoder)
+    // ---------------------------------- shape --------------------------------
+
+    /// Get the plan Cayley would execute the Query with, instead of executing it,
+    /// i.e. to render the traversal as a graph:
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let shape = graph.shape(vertex![ Node("Casablanca") -> OutP(Predicate("name")) => All ]).unwrap();
+    /// for link in shape.links.iter() { println!("{} -> {}", link.source, link.target); }
+    /// ```
+    pub fn shape(&self, query: CompiledQuery) -> GraphResult<Shape> {
+        let query = match self.full_query(query.prefix, &query.value) {
+            Ok(query) => query, Err(error) => return Err(error)
+        };
+        debug!("Getting the shape of query: {}", query);
+        match self.perform_request_to(&Endpoint { url: &self.shape_url, method: &self.method,
+                                                  content_type: self.content_type.as_str() },
+                                      query.into_bytes(), &[], None) {
+            Ok(body) => match str::from_utf8(body.as_slice()) {
+                Err(_) => Err(ResponseParseFailed),
+                Ok(source) => decode_shape(source)
+            },
+            Err(error) => Err(error)
+        }
     }
-}
 
-fn decode_nodes<D: Decoder>(decoder: &mut D) -> Result<Nodes, D::Error> {
-    decoder.read_struct("__unused__", 0, |decoder| {
-        match decoder.read_struct_field("error", 0, |d| -> Result<Option<String>, D::Error> { Decodable::decode(d) }) {
-            Ok(val) => {
-                match val {
-                    Some(ref explanation) =>
-                        Err(decoder.error(format!("Error returned from request: {}", explanation).as_str())),
-                    None => decoder.read_struct_field("result", 1, |decoder| {
-                        decoder.read_option(|decoder, has_value| {
-                            match has_value {
-                                false => Ok(Nodes(Vec::new())),
-                                true => decoder.read_seq(|decoder, len| {
-                                    let mut nodes: Vec<HashMap<String, String>> = Vec::with_capacity(len);
-                                    for i in 0..len {
-                                        nodes.push(match decoder.read_seq_elt(i,
-                                            |decoder| { decode_node(decoder) }) {
-                                                Ok(node) => node,
-                                                Err(err) => return Err(err)
-                                            });
-                                        };
-                                    Ok(Nodes(nodes))
-                                })
-                            }
-                        })
-                    })
-                }
+    // sends a query which Final is not for nodes, returns the `result` items as they are
+    fn find_items(&self, query: CompiledQuery, expected: Expectation) -> GraphResult<Vec<Json>> {
+        match self.send_expecting(query, expected) {
+            Ok(source) => result_items(source.as_str()),
+            Err(error) => Err(error)
+        }
+    }
+
+    fn send_expecting(&self, query: CompiledQuery, expected: Expectation) -> GraphResult<String> {
+        if query.expectation != expected { return Err(ExpectationMismatch(query.expectation)) }
+        match self.check_supported(expected) {
+            Err(error) => return Err(error), Ok(_) => {}
+        };
+        let query = match self.full_query(query.prefix, &query.value) {
+            Ok(query) => query, Err(error) => return Err(error)
+        };
+        debug!("Executing {:?} query: {}", expected, query);
+        match self.perform_request(query.into_bytes(), &[], None) {
+            Ok(body) => match String::from_utf8(body) {
+                Err(_) => Err(ResponseParseFailed),
+                Ok(source) => Ok(source)
             },
-            Err(err) => { println!("err branch"); Err(err) }
+            Err(error) => Err(error)
         }
-    })
+    }
+
+    // ---------------------------------- find_paged ---------------------------
+
+    /// Find the nodes of a Route page by page, `page_size` nodes each, so they are
+    /// never all held in memory. Every page is a separate request, sent when the
+    /// next page is asked for, with `.Skip(<n>).Limit(<page_size>).All()` appended
+    /// to the Route; the pages end with the first one having less than `page_size` nodes.
+    /// If a page fails, its error is the last item.
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// for page in graph.find_paged(vertex![ AnyNode -> OutP(Predicate("follows")) ], 1000) {
+    ///     let Nodes(nodes) = page.unwrap();
+    ///     ...
+    /// }
+    /// ```
+    pub fn find_paged(&self, route: CompiledRoute, page_size: usize) -> Pages {
+        Pages { graph: self, route: route, page_size: page_size, skip: 0, done: page_size == 0 }
+    }
+
+    // ---------------------------------- find_many ----------------------------
+
+    /// Find nodes for several queries, one after another, returning a result
+    /// for every query in the same order, so you may see which of them failed.
+    /// With `BatchMode::AbortOnError`, the first failed query is the last one
+    /// performed, and its error is the last item in the returned list.
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let results = graph.find_many(vec![ vertex![ Node("A") => All ],
+    ///                                     vertex![ Node("B") => All ] ], CollectAll);
+    /// ```
+    pub fn find_many(&self, queries: Vec<CompiledQuery>, mode: BatchMode) -> Vec<GraphResult<Nodes>> {
+        let mut results = Vec::with_capacity(queries.len());
+        for query in queries.into_iter() {
+            let result = self.find(query);
+            let failed = result.is_err();
+            results.push(result);
+            if failed && mode == BatchMode::AbortOnError { break }
+        }
+        results
+    }
+
+    // ---------------------------------- exec_many ----------------------------
+
+    /// Find nodes for several queries with a single request, returning the nodes
+    /// of every query in the same order. The queries are sent as one script, with
+    /// a marker object emitted after the nodes of every query, so the response is
+    /// split back by these markers. The Morphisms several queries follow are
+    /// declared once for the whole batch, and if two different Morphisms have
+    /// the same name, the batch fails with `QueryCompilationFailed`. Unlike
+    /// `find_many`, if any query fails, the whole batch fails. Only the queries
+    /// finished with node Finals, like `All()`, may be batched.
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let found = graph.exec_many(&[ vertex![ Node("A") => All ],
+    ///                                vertex![ Node("B") -> OutP(Predicate("follows")) => All ] ]).unwrap();
+    /// ```
+    pub fn exec_many(&self, queries: &[CompiledQuery]) -> GraphResult<Vec<Nodes>> {
+        if queries.is_empty() { return Ok(Vec::new()) }
+        let mut script = String::new();
+        for (index, query) in queries.iter().enumerate() {
+            if query.expectation != NodeSequence { return Err(ExpectationMismatch(query.expectation)) }
+            script.push_str(&format!("{};{}.Emit({{\"{}\":\"{}\"}});",
+                                     query.value, self.root_name, BATCH_END_KEY, index));
+        }
+        let prefixes: Vec<&str> = queries.iter().map(|query| query.prefix.as_str()).collect();
+        let prefix = match merge_prefixes(&prefixes) {
+            Some(prefix) => prefix, None => return Err(QueryCompilationFailed(None))
+        };
+        let script = match self.full_query(prefix, &script) {
+            Ok(script) => script, Err(error) => return Err(error)
+        };
+        // the transforms and the validators are applied to the nodes of every query, not to the whole batch
+        let found = match self.exec_unchecked(script, NodeSequence, None, None) {
+            Ok((Nodes(nodes), _)) => nodes,
+            Err(error) => return Err(error)
+        };
+        let mut results = Vec::with_capacity(queries.len());
+        let mut current: Vec<Node> = Vec::new();
+        for node in found.into_iter() {
+            if !is_batch_end(&node, results.len()) { current.push(node); continue }
+            self.transform_values(&mut current);
+            let nodes = Nodes(current);
+            match self.check_result(&nodes) {
+                Ok(_) => results.push(nodes),
+                Err(error) => return Err(error)
+            };
+            current = Vec::new();
+        }
+        if results.len() != queries.len() || !current.is_empty() { return Err(ResponseParseFailed) }
+        Ok(results)
+    }
+
+    // ---------------------------------- find_jsonl ---------------------------
+
+    /// Find nodes with the Query implementation and write them to `output` in
+    /// [JSON Lines](http://jsonlines.org/) format, one node object per line,
+    /// returns the number of nodes written
+    ///
+    /// ```ignore
+    /// use std::io::stdout;
+    /// let graph = Graph::default().unwrap();
+    /// graph.find_jsonl(vertex![ AnyNode => All ], &mut stdout()).unwrap();
+    /// ```
+    pub fn find_jsonl<W: Write>(&self, query: CompiledQuery, output: &mut W) -> GraphResult<usize> {
+        match self.find(query) {
+            Ok(nodes) => nodes.write_jsonl(output),
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_by_degree -----------------------
+
+    /// Find the nodes of a given route which have at least `min` outgoing edges
+    /// labeled with `predicate`, each of them returned as `{ "id": <node> }`
+    ///
+    /// Cayley can't count edges on its side, so every edge is requested
+    /// (as `<route>.As("__degree_source").Out(<predicate>).All()`) and they're counted
+    /// here, keep this in mind when using it with routes leading to a lot of nodes.
+    /// Only the nodes having some edges are found this way, so `min` of `0` finds
+    /// the same nodes as `min` of `1`. The edges are requested with neither value
+    /// transforms nor result validators applied, so these never see them.
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let hubs = graph.find_by_degree(vertex![ AnyNode ], "follows", 2).unwrap();
+    /// ```
+    pub fn find_by_degree(&self, base: CompiledRoute, predicate: &str, min: usize) -> GraphResult<Nodes> {
+        let route = match Trail::compile_path_at(&self.root_name, vec![ As(Tag(DEGREE_TAG)), OutP(Predicate(predicate)) ].into_boxed_slice()) {
+            Some(path) => base + path,
+            None => return Err(QueryCompilationFailed(None))
+        };
+        let query = match self.full_query(route.prefix, &(route.value + ".All()")) {
+            Ok(query) => query, Err(error) => return Err(error)
+        };
+        match self.exec_unchecked(query, NodeSequence, None, None) {
+            Ok((Nodes(edges), _)) => {
+                let mut order: Vec<String> = Vec::new();
+                let mut degrees: HashMap<String, usize> = HashMap::new();
+                for edge in edges.iter() {
+                    match edge.get(DEGREE_TAG) {
+                        Some(source) => {
+                            if !degrees.contains_key(source) { order.push(source.clone()); }
+                            *degrees.entry(source.clone()).or_insert(0) += 1;
+                        },
+                        None => {}
+                    }
+                }
+                Ok(Nodes(order.into_iter().filter(|source| degrees[source] >= min).map(|source| {
+                    let mut node = HashMap::new();
+                    node.insert("id".to_string(), source);
+                    Node(node)
+                }).collect()))
+            },
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_nodes ---------------------------
+
+    /// Look up several nodes by their ids with a single `g.V(<ids>).All()`
+    /// query, returns the found nodes keyed by id; the ids not present in
+    /// the graph are just absent from the result. The nodes are returned as
+    /// Cayley sent them, with neither value transforms nor result validators applied
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let found = graph.find_nodes(&["A", "B", "Z"]).unwrap();
+    /// assert!(found.contains_key("A"));
+    /// ```
+    pub fn find_nodes(&self, ids: &[&str]) -> GraphResult<HashMap<String, HashMap<String, String>>> {
+        if ids.is_empty() { return Ok(HashMap::new()) }
+        let query = match Vertex::compile_query_at(&self.root_name, NodeSelector::Nodes(ids.to_vec()),
+                                                   Vec::new().into_boxed_slice(), All) {
+            Some(query) => query,
+            None => return Err(QueryCompilationFailed(None))
+        };
+        match self.find_unchecked(query) {
+            Ok(Nodes(nodes)) => Ok(nodes.into_iter().filter_map(|node| {
+                match node.primary().map(|id| id.to_string()) {
+                    Some(id) if ids.contains(&id.as_str()) => Some((id, node.0)),
+                    _ => None
+                }
+            }).collect()),
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_ci ------------------------------
+
+    /// Find nodes which have a `predicate` edge to a node equal to `value` ignoring
+    /// the case, with a single query where Cayley matches the nodes with a regular
+    /// expression, `g.V().Filter(regex("(?i)^<value>$")).In(<predicate>).Unique().All()`
+    ///
+    /// Cayley can't use its index for a regular expression, so it checks the value of
+    /// every node in the graph against it, which is much slower than an exact `Has`
+    /// lookup on large graphs: prefer exact queries when the case is known.
+    pub fn find_ci(&self, predicate: &str, value: &str) -> GraphResult<Nodes> {
+        let pattern = format!("(?i)^{}$", escape_regex(value));
+        match Vertex::compile_query_at(&self.root_name, NodeSelector::AnyNode,
+                                       vec![ Filter(Comparison::Regex(pattern.as_str())),
+                                             InP(Predicate(predicate)), Unique ].into_boxed_slice(),
+                                       All) {
+            Some(query) => self.find(query),
+            None => Err(QueryCompilationFailed(None))
+        }
+    }
+
+    // ---------------------------------- relationships ------------------------
+
+    /// Get the outgoing edges of a node as `(predicate, target)` pairs, with
+    /// a single query, `g.V(<from>).Out(null,"predicate").All()`, where every
+    /// traversed predicate is tagged along with the node it leads to. The edges
+    /// are requested with neither value transforms nor result validators applied
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// for (predicate, target) in graph.relationships("C").unwrap() { ... }
+    /// ```
+    pub fn relationships(&self, from: &str) -> GraphResult<Vec<(String, String)>> {
+        let query = match Vertex::compile_query_at(&self.root_name, NodeSelector::Node(from),
+                                                   vec![ OutT(Tag("predicate")) ].into_boxed_slice(),
+                                                   All) {
+            Some(query) => query,
+            None => return Err(QueryCompilationFailed(None))
+        };
+        match self.find_unchecked(query) {
+            Ok(Nodes(targets)) => Ok(targets.iter().filter_map(|target| {
+                match (target.get("predicate"), target.get("id")) {
+                    (Some(predicate), Some(id)) => Some((predicate.clone(), id.clone())),
+                    _ => None
+                }
+            }).collect()),
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_nested --------------------------
+
+    /// Find anything with the Query implementation, when the result items may be
+    /// not the flat nodes but arrays or objects of nodes, and return them as
+    /// [Value](./enum.Value.html)s
+    pub fn find_nested(&self, query: CompiledQuery) -> GraphResult<Vec<Value>> {
+        let query = match self.full_query(query.prefix, &query.value) {
+            Ok(query) => query, Err(error) => return Err(error)
+        };
+        debug!("Executing nested query: {}", query);
+        match self.perform_request(query.into_bytes(), &[], None) {
+            Ok(body) => match str::from_utf8(body.as_slice()) {
+                Err(_) => Err(ResponseParseFailed),
+                Ok(source) => Value::decode_result(source)
+            },
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_json ----------------------------
+
+    /// Find anything with the Query implementation and return the whole `result`
+    /// array from Cayley as [Json](http://doc.rust-lang.org/rustc-serialize/rustc_serialize/json/enum.Json.html),
+    /// to navigate it dynamically without defining any types for it
+    pub fn find_json(&self, query: CompiledQuery) -> GraphResult<Json> {
+        let query = match self.full_query(query.prefix, &query.value) {
+            Ok(query) => query, Err(error) => return Err(error)
+        };
+        debug!("Executing query for JSON: {}", query);
+        match self.perform_request(query.into_bytes(), &[], None) {
+            Ok(body) => match str::from_utf8(body.as_slice()) {
+                Err(_) => Err(ResponseParseFailed),
+                Ok(source) => result_items(source).map(Json::Array)
+            },
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_typed ---------------------------
+
+    /// Find nodes with the Query implementation and decode every one of them
+    /// into your own `Decodable` type, instead of picking the values from `Node`s:
+    ///
+    /// ```ignore
+    /// #[derive(RustcDecodable)]
+    /// struct Movie { id: String, name: String }
+    ///
+    /// let graph = Graph::default().unwrap();
+    /// let movies: Vec<Movie> =
+    ///     graph.find_typed(vertex![ AnyNode -> Has(Predicate("name"), Node("Casablanca"))
+    ///                                       -> Save(Predicate("name"), Tag("name")) => All ]).unwrap();
+    /// ```
+    ///
+    /// Queries with other Finals than the node ones, like `All()`, fail with
+    /// `ExpectationMismatch`, without sending.
+    pub fn find_typed<T: Decodable>(&self, query: CompiledQuery) -> GraphResult<Vec<T>> {
+        match self.send_expecting(query, NodeSequence) {
+            Ok(source) => decode_items(source.as_str()),
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_as ------------------------------
+
+    /// Same as `find_typed`, named for the case when the items are decoded from
+    /// tags: every tag set in a query with `As`/`TagWith` or `Save` is a key of a
+    /// result item, so a struct with the fields named the same way as the tags
+    /// (plus `id` for the node itself, if you need it) is filled from these tags:
+    ///
+    /// ```ignore
+    /// #[derive(RustcDecodable)]
+    /// struct Follows { source: String, target: String }
+    ///
+    /// let graph = Graph::default().unwrap();
+    /// let follows: Vec<Follows> =
+    ///     graph.find_as(vertex![ AnyNode -> As(Tag("source"))
+    ///                                    -> Save(Predicate("follows"), Tag("target")) => All ]).unwrap();
+    /// ```
+    pub fn find_as<T: Decodable>(&self, query: CompiledQuery) -> GraphResult<Vec<T>> {
+        self.find_typed(query)
+    }
+
+    // ---------------------------------- find_multitags -----------------------
+
+    /// Find anything with the Query implementation and collect all the values
+    /// every tag had for every node: when Cayley returns an array for a tag, or
+    /// several results for the same node with different values for a tag, these
+    /// values are all kept (once each), rather than the last one overwriting others
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let tags = graph.find_multitags(vertex![ Node("C") -> In(Predicate("follows"), AnyTag)
+    ///                                                    -> As(Tag("follower")) ... => All ]).unwrap();
+    /// let followers: &Vec<String> = &tags[0]["follower"];
+    /// ```
+    pub fn find_multitags(&self, query: CompiledQuery) -> GraphResult<Vec<HashMap<String, Vec<String>>>> {
+        let query = match self.full_query(query.prefix, &query.value) {
+            Ok(query) => query, Err(error) => return Err(error)
+        };
+        debug!("Executing multi-tag query: {}", query);
+        match self.perform_request(query.into_bytes(), &[], None) {
+            Ok(body) => match str::from_utf8(body.as_slice()) {
+                Err(_) => Err(ResponseParseFailed),
+                Ok(source) => decode_multitags(source)
+            },
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- morphism -----------------------------
+
+    /// Get a compiled Morphism named `name`, compiling it with `compile` only if
+    /// this Graph has no Morphism with this name yet, so a fixed set of Morphisms
+    /// your application uses in queries is compiled only once:
+    ///
+    /// ```ignore
+    /// let film_to_actor = graph.morphism("fta", || {
+    ///     morphism![ "fta" -> OutP(Predicate("/film/film/starring"))
+    ///                      -> OutP(Predicate("/film/performance/actor")) ]
+    /// });
+    /// graph.find(vertex![ AnyNode -> Follow(&film_to_actor) => All ]).unwrap();
+    /// ```
+    pub fn morphism<F>(&self, name: &str, compile: F) -> Rc<CompiledReuse>
+                       where F: FnOnce() -> CompiledReuse {
+        match self.morphisms.borrow().get(name) {
+            Some(cached) => return cached.clone(),
+            None => {}
+        };
+        let compiled = Rc::new(compile());
+        self.morphisms.borrow_mut().insert(name.to_string(), compiled.clone());
+        compiled
+    }
+
+    /// Forget all the Morphisms compiled with `Graph::morphism`
+    pub fn clear_morphism_cache(&self) {
+        self.morphisms.borrow_mut().clear()
+    }
+
+    // ---------------------------------- save ---------------------------------
+
+    /// Cayley doesn't keep Morphisms between requests, so a saved Morphism is
+    /// declared before every query this Graph compiles and sends (with `find` and
+    /// others, but not with `exec` or `exec_raw`, which send the query as it is),
+    /// so the Javascript parts of these queries may refer it by its name. A Morphism
+    /// saved with the same name as some saved before replaces it:
+    ///
+    /// ```ignore
+    /// let mut graph = Graph::default().unwrap();
+    /// graph.save(morphism![ "fta" -> OutP(Predicate("/film/film/starring"))
+    ///                             -> OutP(Predicate("/film/performance/actor")) ]);
+    /// // sends `var fta = g.M().Out(..).Out(..);g.V("Casablanca").ForEach(..)`
+    /// graph.find_emitted(vertex![ Node("Casablanca")
+    ///                             => ForEach("g.V(d.id).Follow(fta).ForEach(function(a){ g.Emit(a) })") ]).unwrap();
+    /// ```
+    pub fn save(&mut self, morphism: CompiledReuse) {
+        self.saved.retain(|saved| saved.name != morphism.name);
+        self.saved.push(morphism);
+    }
+
+    // ---------------------------------- to_gremlin ---------------------------
+
+    /// The query exactly as `find` sends it: with the saved Morphisms declared
+    /// before it, see `Graph::save`; fails with `RootNameMismatch` if the query is
+    /// not compiled for the root name of the Graph, see `Graph::with_root_name`
+    pub fn to_gremlin(&self, query: &CompiledQuery) -> GraphResult<String> {
+        self.full_query(query.prefix.clone(), &query.value)
+    }
+
+    // ---------------------------------- write --------------------------------
+
+    /// Write the quads into Cayley, returns the number of quads written,
+    /// as Cayley reports it
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// graph.write(&[ Quad::new("/zh/new_movie", "name", "New Movie").unwrap() ]).unwrap();
+    /// ```
+    pub fn write(&self, quads: &[Quad]) -> GraphResult<usize> {
+        self.send_quads(&self.write_url, quads)
+    }
+
+    // ---------------------------------- delete -------------------------------
+
+    /// Delete the quads from Cayley, returns the number of quads deleted,
+    /// as Cayley reports it. If some quad doesn't exist, fails with `QuadNotFound`
+    /// containing Cayley's explanation
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// graph.delete(&[ Quad::new("/zh/new_movie", "name", "New Movie").unwrap() ]).unwrap();
+    /// ```
+    pub fn delete(&self, quads: &[Quad]) -> GraphResult<usize> {
+        self.send_quads(&self.delete_url, quads)
+    }
+
+    fn send_quads(&self, url: &Url, quads: &[Quad]) -> GraphResult<usize> {
+        let body = match json_encode(&quads) {
+            Ok(json) => json,
+            Err(_) => return Err(OutputFailed(IoError::new(InvalidInput, "quads could not be encoded to JSON")))
+        };
+        debug!("Sending {} quads to {}", quads.len(), url);
+        match self.perform_request_to(&Endpoint { url: url, method: &Method::Post,
+                                                  content_type: JSON_CONTENT_TYPE },
+                                      body.into_bytes(), &[], None) {
+            Ok(body) => match str::from_utf8(body.as_slice()) {
+                Err(_) => Err(ResponseParseFailed),
+                Ok(source) => decode_quads_count(source)
+            },
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- exec ---------------------------------
+
+    /// Find nodes using raw pre-compiled query string and return them parsed
+    ///
+    /// If you want to run just the pure stringified Gremlin queries, bypassing
+    /// the string concatenation performed with `path::` module members, this
+    /// method is for you.
+    ///
+    /// ```ignore
+    /// use cayley::Graph;
+    /// let graph = Graph::default().unwrap();
+    /// graph.exec("g.V(\"foo\").In(\"bar\").All()".to_string()).unwrap();
+    /// ```
+    ///
+    /// If Cayley splits the result into pages and returns a `cursor` with
+    /// a page, the query is re-sent with this cursor until all the pages
+    /// are received, and nodes from all of them are returned together.
+    pub fn exec(&self, query: String, expectation: Expectation) -> GraphResult<Nodes> {
+        self.exec_traced(query, expectation, None)
+    }
+
+    // ---------------------------------- exec_raw -----------------------------
+
+    /// Send a raw query string and return the response body as Cayley sent it,
+    /// with no expectation checks and no decoding, i.e. to cache it or to parse
+    /// the results of the Finals the driver doesn't know about
+    ///
+    /// ```ignore
+    /// let graph = Graph::default().unwrap();
+    /// let body = graph.exec_raw("g.V(\"foo\").All()".to_string()).unwrap();
+    /// ```
+    pub fn exec_raw(&self, query: String) -> GraphResult<Vec<u8>> {
+        debug!("Executing raw query: {}", query);
+        self.perform_request(query.into_bytes(), &[], None)
+    }
+
+    // ---------------------------------- find_traced --------------------------
+
+    /// Same as `find`, but every log line written for this query is marked
+    /// with `trace_id`, and it is sent to Cayley in `X-Request-ID` header, so
+    /// you may match the query with the request of your application it was issued for.
+    /// The id may contain only printable ASCII characters, otherwise it fails with
+    /// `InvalidTraceId` without sending the query
+    pub fn find_traced(&self, query: CompiledQuery, trace_id: &str) -> GraphResult<Nodes> {
+        if !is_header_safe(trace_id) { return Err(InvalidTraceId(trace_id.to_string())) }
+        match self.full_query(query.prefix, &query.value) {
+            Ok(full) => self.exec_traced(full, query.expectation, Some(trace_id)),
+            Err(error) => Err(error)
+        }
+    }
+
+    fn exec_traced(&self, query: String, expectation: Expectation, trace: Option<&str>) -> GraphResult<Nodes> {
+        match self.exec_measured(query, expectation, trace, None) {
+            Ok((nodes, _)) => Ok(nodes),
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_with_stats ----------------------
+
+    /// Same as `find`, but also returns [QueryStats](./struct.QueryStats.html),
+    /// what it took to perform the query, measured on the driver side
+    pub fn find_with_stats(&self, query: CompiledQuery) -> GraphResult<(Nodes, QueryStats)> {
+        match self.full_query(query.prefix, &query.value) {
+            Ok(full) => self.exec_measured(full, query.expectation, None, None),
+            Err(error) => Err(error)
+        }
+    }
+
+    // ---------------------------------- find_capped --------------------------
+
+    /// Same as `find`, but the query is sent with `limit=<cap>` URL parameter,
+    /// so Cayley versions supporting it return no more than `cap` nodes, whatever
+    /// `GetLimit` there is in the query itself. The query is not changed, so for
+    /// the versions not supporting it, the nodes over `cap` are dropped after they
+    /// are received, and no more pages are requested once there are `cap` nodes.
+    pub fn find_capped(&self, query: CompiledQuery, cap: usize) -> GraphResult<Nodes> {
+        let full = match self.full_query(query.prefix, &query.value) {
+            Ok(full) => full, Err(error) => return Err(error)
+        };
+        match self.exec_measured(full, query.expectation, None, Some(cap)) {
+            Ok((nodes, _)) => Ok(nodes),
+            Err(error) => Err(error)
+        }
+    }
+
+    // same as `find`, but for the queries the driver sends itself, see `exec_unchecked`
+    fn find_unchecked(&self, query: CompiledQuery) -> GraphResult<Nodes> {
+        match self.full_query(query.prefix, &query.value) {
+            Ok(full) => self.exec_unchecked(full, query.expectation, None, None).map(|(nodes, _)| nodes),
+            Err(error) => Err(error)
+        }
+    }
+
+    fn exec_measured(&self, query: String, expectation: Expectation, trace: Option<&str>, cap: Option<usize>)
+                     -> GraphResult<(Nodes, QueryStats)> {
+        match self.exec_unchecked(query, expectation, trace, cap) {
+            Ok((Nodes(mut nodes), stats)) => {
+                self.transform_values(&mut nodes);
+                let nodes = Nodes(nodes);
+                match self.check_result(&nodes) {
+                    Ok(_) => Ok((nodes, stats)),
+                    Err(error) => Err(error)
+                }
+            },
+            Err(error) => Err(error)
+        }
+    }
+
+    // same as `exec_measured`, but neither value transforms nor result validators are
+    // applied, i.e. for the queries the driver sends to get not the result itself,
+    // but something to build it from
+    fn exec_unchecked(&self, query: String, expectation: Expectation, trace: Option<&str>, cap: Option<usize>)
+                      -> GraphResult<(Nodes, QueryStats)> {
+        let started = Instant::now();
+        let mut stats = QueryStats { duration: Duration::from_secs(0),
+                                     request_bytes: 0, response_bytes: 0, requests: 0 };
+        debug!("{}Executing query: {}", trace_mark(trace), query);
+        match self.check_supported(expectation) {
+            Err(error) => Err(error),
+            Ok(_) => {
+                let mut found: Vec<Node> = Vec::new();
+                let received = self.follow_cursors(&query, trace, cap, &mut stats, |body| {
+                    match Graph::decode_traversal(body) {
+                        Ok(Page { nodes: Nodes(nodes), cursor }) => {
+                            found.extend(nodes.into_iter());
+                            // the cap is applied here too, for Cayley versions ignoring the parameter
+                            match cap { Some(cap) => found.truncate(cap), None => {} };
+                            Ok((found.len(), cursor))
+                        },
+                        Err(error) => Err(error)
+                    }
+                });
+                match received {
+                    Ok(_) => {
+                        stats.duration = started.elapsed();
+                        Ok((Nodes(found), stats))
+                    },
+                    Err(error) => Err(error)
+                }
+            }
+        }
+    }
+
+    // send a query, then send it again with every cursor Cayley returns, until there are
+    // no more pages or there are `cap` items; every response body is passed to `receive`,
+    // which returns the number of items received so far and the cursor found in the body
+    fn follow_cursors<F>(&self, query: &str, trace: Option<&str>, cap: Option<usize>,
+                         stats: &mut QueryStats, mut receive: F) -> GraphResult<()>
+                         where F: FnMut(Vec<u8>) -> GraphResult<(usize, Option<String>)> {
+        let mut cursor: Option<String> = None;
+        let mut followed: Vec<String> = Vec::new();
+        let cap_str = cap.map(|cap| cap.to_string());
+        loop {
+            stats.requests += 1;
+            stats.request_bytes += query.len();
+            let body = {
+                let mut params: Vec<(&str, &str)> = Vec::new();
+                match cap_str { Some(ref cap) => params.push(("limit", cap.as_str())), None => {} };
+                match cursor { Some(ref cursor) => params.push(("cursor", cursor.as_str())), None => {} };
+                self.perform_request(query.to_string().into_bytes(), &params, trace)
+            };
+            let (received, next) = match body {
+                Ok(body) => {
+                    stats.response_bytes += body.len();
+                    match receive(body) {
+                        Ok(page) => page,
+                        Err(error) => return Err(error)
+                    }
+                },
+                Err(error) => return Err(error)
+            };
+            let capped = cap.map_or(false, |cap| received >= cap);
+            match next {
+                Some(next) if !capped => {
+                    // a cursor met again would make the pages go round forever
+                    if followed.contains(&next) { return Err(CursorRepeated(next)) }
+                    debug!("{}Following cursor: {}", trace_mark(trace), next);
+                    followed.push(next.clone());
+                    cursor = Some(next)
+                },
+                _ => return Ok(())
+            }
+        }
+    }
+
+    // apply the validators set with `Graph::with_result_validator`
+    fn check_result(&self, nodes: &Nodes) -> GraphResult<()> {
+        for validator in self.validators.iter() {
+            match validator(nodes) {
+                Err(explanation) => return Err(ResultRejected(explanation)),
+                Ok(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn perform_request(&self, body: Vec<u8>, params: &[(&str, &str)], trace: Option<&str>) -> GraphResult<Vec<u8>> {
+        let endpoint = Endpoint { url: &self.url, method: &self.method, content_type: self.content_type.as_str() };
+        let mut attempt = 0;
+        loop {
+            match self.perform_request_to(&endpoint, body.clone(), params, trace) {
+                Err(RequestFailed(..)) | Err(RequestIoFailed(..)) if attempt < self.retries => {
+                    let backoff = RETRY_BACKOFF_MS << attempt;
+                    attempt += 1;
+                    debug!("{}Request failed, retry {} of {} in {}ms", trace_mark(trace), attempt, self.retries, backoff);
+                    thread::sleep(Duration::from_millis(backoff));
+                },
+                result => return result
+            }
+        }
+    }
+
+    fn perform_request_to(&self, endpoint: &Endpoint, body: Vec<u8>, params: &[(&str, &str)], trace: Option<&str>)
+                          -> GraphResult<Vec<u8>> {
+        // every request passes here, whichever method sent it
+        match self.last_query {
+            Some(ref last_query) => *last_query.borrow_mut() = Some(String::from_utf8_lossy(body.as_slice()).into_owned()),
+            None => {}
+        };
+        // MQL queries and quads are JSON, only Gremlin queries are checked
+        let gremlin = endpoint.url == &self.url || endpoint.url == &self.shape_url;
+        if cfg!(debug_assertions) && gremlin {
+            match validate_query(String::from_utf8_lossy(body.as_slice()).as_ref()) {
+                Err(position) => {
+                    debug!("{}Query is malformed at {}", trace_mark(trace), position);
+                    return Err(QueryCompilationFailed(Some(position)))
+                },
+                Ok(_) => {}
+            }
+        }
+        match self.breaker {
+            None => self.send_request(endpoint, body, params, trace),
+            Some(ref breaker) => {
+                match breaker.state.get() {
+                    CircuitState::Open(since) => {
+                        if since.elapsed() < breaker.cooldown { return Err(CircuitOpen) }
+                        breaker.state.set(CircuitState::HalfOpen)
+                    },
+                    _ => {}
+                };
+                let result = self.send_request(endpoint, body, params, trace);
+                let failed = match result {
+                    Err(MalformedRequest(..)) | Err(RequestFailed(..)) |
+                    Err(RequestIoFailed(..)) | Err(RequestTimedOut(..)) => true,
+                    // Cayley answering with `5xx` is as unavailable as Cayley not answering
+                    Err(HttpStatus(ref status, _)) => status.is_server_error(),
+                    _ => false
+                };
+                breaker.state.set(match (failed, breaker.state.get()) {
+                    (true, CircuitState::Closed(failures)) if failures + 1 < breaker.threshold =>
+                        CircuitState::Closed(failures + 1),
+                    (true, _) => {
+                        debug!("{}Circuit to {} is open now", trace_mark(trace), endpoint.url);
+                        CircuitState::Open(Instant::now())
+                    },
+                    (false, _) => CircuitState::Closed(0)
+                });
+                result
+            }
+        }
+    }
+
+    fn send_request(&self, endpoint: &Endpoint, body: Vec<u8>, params: &[(&str, &str)], trace: Option<&str>)
+                    -> GraphResult<Vec<u8>> {
+        let mut url = endpoint.url.clone();
+        let mut pairs: Vec<(&str, &str)> = params.to_vec();
+        if !pairs.is_empty() { url.set_query_from_pairs(pairs.clone().into_iter()) };
+        // queries are sent with GET only if it was asked for and the URL is not too long for it
+        let as_get = match (endpoint.method, str::from_utf8(body.as_slice())) {
+            (&Method::Get, Ok(query)) => {
+                let mut get_url = url.clone();
+                pairs.push(("query", query));
+                get_url.set_query_from_pairs(pairs.into_iter());
+                match get_url.serialize().len() <= MAX_GET_URL_LENGTH {
+                    true => { url = get_url; true },
+                    false => { debug!("Query is too long for GET, sending it with POST"); false }
+                }
+            },
+            _ => false
+        };
+        let payload: &[u8] = if as_get { &[] } else { body.as_slice() };
+        let mut headers = Headers::new();
+        if !as_get {
+            headers.set(ContentLength(payload.len() as u64));
+            headers.set_raw("Content-Type", vec![endpoint.content_type.as_bytes().to_vec()]);
+        };
+        headers.set(UserAgent(self.user_agent.clone()));
+        match trace {
+            Some(trace_id) => headers.set_raw("X-Request-ID", vec![trace_id.as_bytes().to_vec()]),
+            None => {}
+        };
+        let response = self.client.request(if as_get { Method::Get } else { Method::Post }, url)
+                                  .headers(headers).body(payload).send();
+        match response {
+            Err(HttpError::Io(ref error)) if self.timed_out(error) => Err(RequestTimedOut(self.timeout.unwrap())),
+            Err(error) => Err(RequestFailed(error, body)),
+            Ok(response) => {
+                let status = response.status;
+                match self.read_response(response, body, endpoint.url, trace) {
+                    Ok(ref response_body) if !status.is_success() => {
+                        debug!("{}Request to {} was rejected with {}", trace_mark(trace), endpoint.url, status);
+                        Err(HttpStatus(status, String::from_utf8_lossy(response_body).into_owned()))
+                    },
+                    result => result
+                }
+            }
+        }
+
+    }
+
+    fn read_response<R: Read>(&self, response: R, body: Vec<u8>, url: &Url, trace: Option<&str>) -> GraphResult<Vec<u8>> {
+        let mut response_body = Vec::new();
+        let read = match self.max_response_bytes {
+            None => { let mut response = response; response.read_to_end(&mut response_body) },
+            // read one byte over the limit, to know if it was exceeded
+            Some(limit) => response.take(limit + 1).read_to_end(&mut response_body)
+        };
+        match read {
+            Err(ref error) if self.timed_out(error) => Err(RequestTimedOut(self.timeout.unwrap())),
+            Err(error) => Err(RequestIoFailed(error, body)),
+            Ok(_) => match self.max_response_bytes {
+                Some(limit) if response_body.len() as u64 > limit => Err(ResponseTooLarge(limit)),
+                _ => {
+                    debug!("{}Request to {} succeeded", trace_mark(trace), url);
+                    Ok(response_body)
+                }
+            }
+        }
+    }
+
+    // a socket with a timeout set fails with `WouldBlock` or `TimedOut`, depending on the platform
+    fn timed_out(&self, error: &IoError) -> bool {
+        self.timeout.is_some() && (error.kind() == WouldBlock || error.kind() == TimedOut)
+    }
+
+    // extract JSON nodes (and a cursor, if there is one) from response
+    fn decode_traversal(source: Vec<u8>) -> GraphResult<Page> {
+        match str::from_utf8(source.as_slice()) {
+            Err(_) => Err(ResponseParseFailed),
+            Ok(traversal_json) => {
+                debug!("start decoding \n===\n{:.200}\n...\n===\n", traversal_json);
+                match json_decode(traversal_json) {
+                    Err(error) => Err(match partial_result(traversal_json) {
+                        Some(partial) => partial,
+                        None => match server_error(traversal_json) {
+                            Some(server_error) => server_error,
+                            None => DecodingFailed(error, traversal_json.to_string())
+                        }
+                    }),
+                    Ok(page) => {
+                        debug!("Returned: {}", match page { Page { nodes: Nodes(ref val), .. } => val.len() });
+                        Ok(page)
+                    }
+                }
+            }
+        }
+    }
+
+}
+
+// the symbols having a meaning in regular expressions are escaped, so a value is matched as it is
+fn escape_regex(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for symbol in value.chars() {
+        match symbol {
+            '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' => {
+                escaped.push('\\');
+                escaped.push(symbol)
+            },
+            _ => escaped.push(symbol)
+        }
+    }
+    escaped
+}
+
+// the marker `Graph::exec_many` emits after the nodes of the query with given index,
+// a node having the same key, but some other value or other keys, is not one
+fn is_batch_end(node: &Node, index: usize) -> bool {
+    node.len() == 1 && node.get(BATCH_END_KEY).map_or(false, |value| *value == index.to_string())
+}
+
+// the explanation Cayley gave in the `error` field of a response, if there is one
+fn server_error(source: &str) -> Option<GraphError> {
+    match Json::from_str(source) {
+        Ok(response) => match response.find("error") {
+            Some(&Json::String(ref explanation)) => Some(ServerError(explanation.clone())),
+            _ => None
+        },
+        Err(_) => None
+    }
+}
+
+// Cayley may return an `error` along with the nodes it managed to find before it,
+// then these nodes are not lost but passed with the error
+fn partial_result(source: &str) -> Option<GraphError> {
+    let response = match Json::from_str(source) {
+        Ok(json) => json,
+        Err(_) => return None
+    };
+    match (response.find("error"), response.find("result")) {
+        (Some(&Json::String(ref message)), Some(&Json::Array(ref items))) => {
+            let mut nodes = Vec::with_capacity(items.len());
+            for item in items.iter() {
+                match decode_node(&mut JsonDecoder::new(item.clone())) {
+                    Ok(node) => nodes.push(node),
+                    Err(_) => return None
+                }
+            }
+            Some(PartialResult { message: message.clone(), nodes: Nodes(nodes) })
+        },
+        _ => None
+    }
+}
+
+fn decode_count(source: &str) -> GraphResult<i64> {
+    let response = match Json::from_str(source) {
+        Ok(json) => json,
+        Err(error) => return Err(DecodingFailed(DecoderError::ParseError(error), source.to_string()))
+    };
+    match response.find("error") {
+        Some(&Json::String(ref explanation)) =>
+            return Err(ServerError(explanation.clone())),
+        _ => {}
+    };
+    match response.find("result").and_then(|result| result.as_i64()) {
+        Some(count) => Ok(count),
+        None => Err(DecodingFailed(DecoderError::ApplicationError(
+                                       "`result` is not a number".to_string()), source.to_string()))
+    }
+}
+
+// tags are decoded the same way as the nodes, they are just the maps of strings
+fn decode_tags(item: Json) -> GraphResult<HashMap<String, String>> {
+    let source = item.to_string();
+    match decode_node(&mut JsonDecoder::new(item)) {
+        Ok(Node(tags)) => Ok(tags),
+        Err(error) => Err(DecodingFailed(error, source))
+    }
+}
+
+// the shape is not wrapped into `result`, it is the response itself;
+// Cayley omits `nodes` or `links` when there are none
+fn decode_shape(source: &str) -> GraphResult<Shape> {
+    let mut response = match Json::from_str(source) {
+        Ok(json) => json,
+        Err(error) => return Err(DecodingFailed(DecoderError::ParseError(error), source.to_string()))
+    };
+    match response.find("error") {
+        Some(&Json::String(ref explanation)) =>
+            return Err(ServerError(explanation.clone())),
+        _ => {}
+    };
+    match response {
+        Json::Object(ref mut fields) => for key in ["nodes", "links"].iter() {
+            let missing = match fields.get(*key) {
+                None | Some(&Json::Null) => true,
+                Some(_) => false
+            };
+            if missing { fields.insert(key.to_string(), Json::Array(Vec::new())); }
+        },
+        _ => {}
+    };
+    match Decodable::decode(&mut JsonDecoder::new(response)) {
+        Ok(shape) => Ok(shape),
+        Err(error) => Err(DecodingFailed(error, source.to_string()))
+    }
+}
+
+/// Decode the nodes from Cayley response keeping the order of their keys,
+/// see `Graph::find_ordered`. `Json` keeps the keys of an object sorted, so the
+/// response is read event by event instead, and nested values are skipped
+pub fn decode_ordered(source: &str) -> GraphResult<OrderedNodes> {
+    decode_ordered_page(source).map(|(nodes, _)| nodes)
+}
+
+// same as `decode_ordered`, but also returns the cursor to the next page, if there is one
+fn decode_ordered_page(source: &str) -> GraphResult<(OrderedNodes, Option<String>)> {
+    let mut parser = Parser::new(source.chars());
+    let mut nodes: Vec<OrderedNode> = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let event = match parser.next() {
+            Some(event) => event,
+            None => break
+        };
+        let value = match event {
+            JsonEvent::Error(error) => return Err(DecodingFailed(DecoderError::ParseError(error), source.to_string())),
+            JsonEvent::ObjectStart => {
+                if parser.stack().len() == 2 && parser.stack().get(0) == StackElement::Key("result") {
+                    nodes.push(OrderedNode(Vec::new()));
+                }
+                continue
+            },
+            JsonEvent::StringValue(value) => value,
+            JsonEvent::I64Value(value) => value.to_string(),
+            JsonEvent::U64Value(value) => value.to_string(),
+            JsonEvent::F64Value(value) => value.to_string(),
+            JsonEvent::BooleanValue(value) => value.to_string(),
+            _ => continue
+        };
+        let stack = parser.stack();
+        match (stack.len(), stack.get(0)) {
+            (1, StackElement::Key("error")) => return Err(ServerError(value)),
+            (1, StackElement::Key("cursor")) => cursor = Some(value),
+            (3, StackElement::Key("result")) => match (stack.get(2), nodes.last_mut()) {
+                (StackElement::Key(key), Some(&mut OrderedNode(ref mut fields))) => fields.push((key.to_string(), value)),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    Ok((OrderedNodes(nodes), cursor))
+}
+
+// MQL objects may have any JSON values in their fields, strings are taken as they are,
+// everything else is kept as its JSON
+fn decode_mql_items(items: Vec<Json>) -> GraphResult<Vec<HashMap<String, String>>> {
+    let mut objects = Vec::with_capacity(items.len());
+    for item in items.into_iter() {
+        match item {
+            Json::Object(fields) => objects.push(fields.into_iter().map(|(key, value)| {
+                match value {
+                    Json::String(value) => (key, value),
+                    other => (key, other.to_string())
+                }
+            }).collect()),
+            other => return Err(DecodingFailed(DecoderError::ExpectedError("Object".to_string(), other.to_string()),
+                                               other.to_string()))
+        }
+    }
+    Ok(objects)
+}
+
+fn decode_name_sequence(items: Vec<Json>) -> GraphResult<Vec<String>> {
+    let mut names = Vec::with_capacity(items.len());
+    for item in items.into_iter() {
+        match item {
+            Json::String(name) => names.push(name),
+            other => return Err(DecodingFailed(DecoderError::ExpectedError("String".to_string(), other.to_string()),
+                                               other.to_string()))
+        }
+    }
+    Ok(names)
+}
+
+// Cayley reports the quads it wrote or deleted with a message,
+// i.e. `{"result":"Successfully wrote 2 quads."}`, the number is taken from it
+fn decode_quads_count(source: &str) -> GraphResult<usize> {
+    let response = match Json::from_str(source) {
+        Ok(json) => json,
+        Err(error) => return Err(DecodingFailed(DecoderError::ParseError(error), source.to_string()))
+    };
+    match response.find("error") {
+        Some(&Json::String(ref explanation)) if explanation.contains("does not exist") =>
+            return Err(QuadNotFound(explanation.clone())),
+        Some(&Json::String(ref explanation)) =>
+            return Err(ServerError(explanation.clone())),
+        _ => {}
+    };
+    let count = response.find("result").and_then(|result| result.as_string()).and_then(|message| {
+        message.split(' ').filter_map(|word| word.parse::<usize>().ok()).next()
+    });
+    match count {
+        Some(count) => Ok(count),
+        None => Err(DecodingFailed(DecoderError::ApplicationError(
+                                       "no number of quads in `result`".to_string()), source.to_string()))
+    }
+}
+
+fn parse_url(url: String) -> GraphResult<Url> {
+    match Url::parse(url.as_str()) {
+        Ok(parsed) => Ok(parsed),
+        Err(error) => Err(InvalidUrl(error, url))
+    }
+}
+
+// line breaks in a header value would let it add headers of its own
+fn is_header_safe(value: &str) -> bool {
+    value.bytes().all(|byte| byte >= 0x20 && byte < 0x7f)
+}
+
+// a prefix to mark log lines with, when the query is traced
+fn trace_mark(trace: Option<&str>) -> String {
+    match trace {
+        Some(trace_id) => format!("[{}] ", trace_id),
+        None => String::new()
+    }
+}
+
+impl<'g> Iterator for Pages<'g> {
+    type Item = GraphResult<Nodes>;
+
+    fn next(&mut self) -> Option<GraphResult<Nodes>> {
+        if self.done { return None }
+        let value = format!("{}.Skip({}).Limit({}).All()", self.route.value, self.skip, self.page_size);
+        let page = match self.graph.full_query(self.route.prefix.clone(), &value) {
+            Ok(query) => self.graph.exec(query, NodeSequence),
+            Err(error) => Err(error)
+        };
+        match page {
+            Ok(Nodes(nodes)) => {
+                self.skip += nodes.len();
+                self.done = nodes.len() < self.page_size;
+                // the previous page was the last one, but it was full
+                if nodes.is_empty() { return None }
+                Some(Ok(Nodes(nodes)))
+            },
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+
+}
+
+impl Nodes {
+
+    /// The number of nodes found
+    pub fn len(&self) -> usize {
+        match *self { Nodes(ref nodes) => nodes.len() }
+    }
+
+    /// `true` if no nodes were found
+    pub fn is_empty(&self) -> bool {
+        match *self { Nodes(ref nodes) => nodes.is_empty() }
+    }
+
+    /// Iterate over the nodes found
+    pub fn iter(&self) -> ::std::slice::Iter<Node> {
+        match *self { Nodes(ref nodes) => nodes.iter() }
+    }
+
+    /// The `id` of every node found, the nodes without it are skipped
+    pub fn ids(&self) -> Vec<&str> {
+        self.iter().filter_map(|node| node.id()).collect()
+    }
+
+    /// Write nodes to `output` in [JSON Lines](http://jsonlines.org/) format,
+    /// one node object per line, returns the number of nodes written
+    pub fn write_jsonl<W: Write>(&self, output: &mut W) -> GraphResult<usize> {
+        match *self {
+            Nodes(ref nodes) => {
+                for node in nodes.iter() {
+                    let line = match json_encode(node) {
+                        Ok(json) => json,
+                        Err(_) => return Err(OutputFailed(IoError::new(InvalidInput,
+                                                           "node could not be encoded to JSON")))
+                    };
+                    match output.write_all(line.as_bytes()).and_then(|_| output.write_all(b"\n")) {
+                        Err(error) => return Err(OutputFailed(error)),
+                        Ok(_) => {}
+                    }
+                }
+                Ok(nodes.len())
+            }
+        }
+    }
+
+}
+
+impl Node {
+
+    /// Get the node's `id`, if it has one
+    pub fn id(&self) -> Option<&str> {
+        self.get("id").map(|id| id.as_str())
+    }
+
+    /// Get the node's own value, whichever key Cayley has put it under:
+    /// `id` is checked first, then `value`, and if the node has only one
+    /// field at all, this field is taken.
+    pub fn primary(&self) -> Option<&str> {
+        match *self {
+            Node(ref map) => match map.get("id") {
+                Some(id) => Some(id.as_str()),
+                None => match map.get("value") {
+                    Some(value) => Some(value.as_str()),
+                    None => match map.len() {
+                        1 => map.values().next().map(|only| only.as_str()),
+                        _ => None
+                    }
+                }
+            }
+        }
+    }
+
+}
+
+/// `for node in nodes { .. }`, consuming the nodes
+impl IntoIterator for Nodes {
+    type Item = Node;
+    type IntoIter = ::std::vec::IntoIter<Node>;
+
+    fn into_iter(self) -> ::std::vec::IntoIter<Node> {
+        match self { Nodes(nodes) => nodes.into_iter() }
+    }
+
+}
+
+/// `for node in &nodes { .. }`, same as `nodes.iter()`
+impl<'a> IntoIterator for &'a Nodes {
+    type Item = &'a Node;
+    type IntoIter = ::std::slice::Iter<'a, Node>;
+
+    fn into_iter(self) -> ::std::slice::Iter<'a, Node> {
+        self.iter()
+    }
+
+}
+
+/// Every node on its own line, with its keys sorted: `{id: A, name: Alice}`
+impl Display for Nodes {
+
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        let rows: Vec<String> = self.iter().map(|node| node.to_string()).collect();
+        f.write_str(rows.connect("\n").as_str())
+    }
+
+}
+
+/// A node with its keys sorted, so it is the same for every run: `{id: A, name: Alice}`
+impl Display for Node {
+
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        let mut keys: Vec<&String> = self.keys().collect();
+        keys.sort();
+        let fields: Vec<String> = keys.iter().map(|key| format!("{}: {}", key, self[*key])).collect();
+        write!(f, "{{{}}}", fields.connect(", "))
+    }
+
+}
+
+impl Deref for Node {
+    type Target = HashMap<String, String>;
+
+    fn deref(&self) -> &HashMap<String, String> {
+        match *self { Node(ref map) => map }
+    }
+
+}
+
+impl ResultRow {
+
+    /// Get the `id` of the row, if it has one
+    pub fn id(&self) -> Option<&str> {
+        self.get("id").map(|id| id.as_str())
+    }
+
+    /// Get the value of a tag, if the row has one, `id` is not a tag
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        if name == "id" { return None }
+        self.get(name).map(|value| value.as_str())
+    }
+
+    /// All the tags of the row as `(name, value)` pairs, sorted by name, `id` is not a tag
+    pub fn tags(&self) -> Vec<(&str, &str)> {
+        let mut tags: Vec<(&str, &str)> = self.iter().filter(|&(key, _)| key != "id")
+                                              .map(|(key, value)| (key.as_str(), value.as_str())).collect();
+        tags.sort();
+        tags
+    }
+
+}
+
+impl From<Node> for ResultRow {
+    fn from(node: Node) -> ResultRow {
+        match node { Node(map) => ResultRow(map) }
+    }
+}
+
+impl Deref for ResultRow {
+    type Target = HashMap<String, String>;
+
+    fn deref(&self) -> &HashMap<String, String> {
+        match *self { ResultRow(ref map) => map }
+    }
+
+}
+
+// extract the items of the `result` array from Cayley response,
+// `null` result is an empty list
+fn result_items(source: &str) -> GraphResult<Vec<Json>> {
+    let response = match Json::from_str(source) {
+        Ok(json) => json,
+        Err(error) => return Err(DecodingFailed(DecoderError::ParseError(error), source.to_string()))
+    };
+    match response.find("error") {
+        Some(&Json::String(ref explanation)) =>
+            return Err(ServerError(explanation.clone())),
+        _ => {}
+    };
+    match response.find("result") {
+        None | Some(&Json::Null) => Ok(Vec::new()),
+        Some(&Json::Array(ref items)) => Ok(items.clone()),
+        // Finals like `ToValue()` return a single item, not wrapped in an array
+        Some(item @ &Json::Object(_)) => Ok(vec![ item.clone() ]),
+        Some(_) => Err(DecodingFailed(DecoderError::ApplicationError(
+                                          "`result` is not an array".to_string()), source.to_string()))
+    }
+}
+
+/// Decode every item of the `result` array from Cayley response into
+/// a given type, see `Graph::find_typed` and `Graph::find_as`
+pub fn decode_items<T: Decodable>(source: &str) -> GraphResult<Vec<T>> {
+    match result_items(source) {
+        Ok(items) => {
+            let mut decoded = Vec::with_capacity(items.len());
+            for item in items.into_iter() {
+                match Decodable::decode(&mut JsonDecoder::new(item)) {
+                    Ok(value) => decoded.push(value),
+                    Err(error) => return Err(DecodingFailed(error, source.to_string()))
+                }
+            }
+            Ok(decoded)
+        },
+        Err(error) => Err(error)
+    }
+}
+
+/// Decode the `result` array from Cayley response into tags with all their
+/// values per node, see `Graph::find_multitags`
+pub fn decode_multitags(source: &str) -> GraphResult<Vec<HashMap<String, Vec<String>>>> {
+    let items = match result_items(source) {
+        Ok(items) => items,
+        Err(error) => return Err(error)
+    };
+    let mut nodes: Vec<HashMap<String, Vec<String>>> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for item in items.iter() {
+        let object = match item.as_object() {
+            Some(object) => object,
+            None => return Err(DecodingFailed(DecoderError::ApplicationError(
+                                                  "result item is not an object".to_string()), source.to_string()))
+        };
+        let position = match object.get("id").and_then(|id| id.as_string()) {
+            Some(id) => match positions.get(id).map(|position| *position) {
+                Some(position) => position,
+                None => { positions.insert(id.to_string(), nodes.len()); nodes.push(HashMap::new()); nodes.len() - 1 }
+            },
+            None => { nodes.push(HashMap::new()); nodes.len() - 1 }
+        };
+        let tags = &mut nodes[position];
+        for (tag, value) in object.iter() {
+            let values = tags.entry(tag.clone()).or_insert(Vec::new());
+            let received: Vec<&Json> = match *value {
+                Json::Array(ref items) => items.iter().collect(),
+                ref single => vec![single]
+            };
+            for received_value in received.into_iter() {
+                let text = match *received_value {
+                    Json::String(ref text) => text.clone(),
+                    ref other => other.to_string()
+                };
+                if !values.contains(&text) { values.push(text) }
+            }
+        }
+    }
+    Ok(nodes)
+}
+
+impl Value {
+
+    /// Extract the `result` of a Cayley response as a list of values,
+    /// `null` result is an empty list
+    pub fn decode_result(source: &str) -> GraphResult<Vec<Value>> {
+        match result_items(source) {
+            Ok(items) => Ok(items.iter().map(Value::from_json).collect()),
+            Err(error) => Err(error)
+        }
+    }
+
+    fn from_json(json: &Json) -> Value {
+        match *json {
+            Json::Object(ref object) => {
+                if object.values().all(|value| value.is_string()) {
+                    Value::Node(object.iter().map(|(key, value)| {
+                        (key.clone(), value.as_string().unwrap().to_string())
+                    }).collect())
+                } else {
+                    Value::Group(object.iter().map(|(key, value)| {
+                        (key.clone(), Value::from_json(value))
+                    }).collect())
+                }
+            },
+            Json::Array(ref items) => Value::List(items.iter().map(Value::from_json).collect()),
+            Json::String(ref text) => Value::Text(text.clone()),
+            Json::Null => Value::Null,
+            ref scalar => Value::Text(scalar.to_string())
+        }
+    }
+
+}
+
+impl Encodable for Node {
+
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+        match *self { Node(ref map) => map.encode(encoder) }
+    }
+
+}
+
+impl Decodable for Nodes {
+
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, D::Error> {
+        decode_nodes(decoder)
+    }
+}
+
+impl Decodable for Page {
+
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.read_struct("__unused__", 0, |decoder| {
+            let nodes = match decode_nodes_fields(decoder) {
+                Ok(nodes) => nodes, Err(err) => return Err(err)
+            };
+            match decoder.read_struct_field("cursor", 2, |d| -> Result<Option<String>, D::Error> { Decodable::decode(d) }) {
+                Ok(cursor) => Ok(Page { nodes: nodes, cursor: cursor }),
+                Err(err) => Err(err)
+            }
+        })
+    }
+}
+
+fn decode_nodes<D: Decoder>(decoder: &mut D) -> Result<Nodes, D::Error> {
+    decoder.read_struct("__unused__", 0, |decoder| { decode_nodes_fields(decoder) })
+}
+
+fn decode_nodes_fields<D: Decoder>(decoder: &mut D) -> Result<Nodes, D::Error> {
+    match decoder.read_struct_field("error", 0, |d| -> Result<Option<String>, D::Error> { Decodable::decode(d) }) {
+        Ok(val) => {
+            match val {
+                Some(ref explanation) =>
+                    Err(decoder.error(format!("Error returned from request: {}", explanation).as_str())),
+                None => decoder.read_struct_field("result", 1, |decoder| {
+                    decoder.read_option(|decoder, has_value| {
+                        match has_value {
+                            false => Ok(Nodes(Vec::new())),
+                            true => decoder.read_seq(|decoder, len| {
+                                let mut nodes: Vec<Node> = Vec::with_capacity(len);
+                                for i in 0..len {
+                                    nodes.push(match decoder.read_seq_elt(i,
+                                        |decoder| { decode_node(decoder) }) {
+                                            Ok(node) => node,
+                                            Err(err) => return Err(err)
+                                        });
+                                    };
+                                Ok(Nodes(nodes))
+                            })
+                        }
+                    })
+                })
+            }
+        },
+        Err(err) => { println!("err branch"); Err(err) }
+    }
 }
 
-fn decode_node<D: Decoder>(decoder: &mut D) -> Result<HashMap<String, String>, D::Error> {
+fn decode_node<D: Decoder>(decoder: &mut D) -> Result<Node, D::Error> {
     decoder.read_map(|decoder, len| {
         let mut data_map: HashMap<String, String> = HashMap::new();
         for i in 0..len {
@@ -229,6 +2453,6 @@ fn decode_node<D: Decoder>(decoder: &mut D) -> Result<HashMap<String, String>, D
                     Ok(val) => val, Err(err) => return Err(err)
                 });
         }
-        Ok(data_map)
+        Ok(Node(data_map))
     })
 }
@@ -42,8 +42,8 @@
 //! ```ignore
 //! use cayley::{Graph, DefaultVersion};
 //! use cayley::GraphNodes;
-//! use cayley::path::{Vertex, Query}; // Query trait import is required
-//! use cayley::selector::AnyNode;
+//! use cayley::path::Vertex;
+//! use cayley::selectors::AnyNode;
 //!
 //! let graph = Graph::new("localhost", 64210, DefaultVersion).unwrap();
 //! match graph.find(Vertex::start(AnyNode).All()) {
@@ -54,8 +54,9 @@
 //!
 //! So in general it looks like `graph.find(<Query>)`.
 //!
-//! [GraphNodes](./graph/struct.GraphNodes.html) is a wrapper for `Vec<GraphNode>`.
-//! [GraphNode](./graph/struct.GraphNode.html) is a wrapper for `HashMap<String, String>`
+//! [Nodes](./graph/struct.Nodes.html) is a wrapper for `Vec<Node>`.
+//! [Node](./graph/struct.Node.html) is a wrapper for `HashMap<String, String>`, use
+//! `node.primary()` to get its value regardless of it being keyed under `id` or `value`.
 //!
 //! Morphism used this way:
 //!
@@ -64,12 +65,13 @@
 //! use cayley::{Graph, DefaultVersion};
 //! use cayley::path::Vertex as V;
 //! use cayley::path::Morphism as M;
-//! use cayley::path::{Path, Query}; // both traits imports are required
-//! use cayley::selector::{Predicate, Node};
+//! use cayley::path::Traverse; // Traverse trait import is required
+//! use cayley::selectors::{Predicate, Node};
 //!
 //! let graph = Graph::new("localhost", 64210, DefaultVersion).unwrap();
 //! let mut follows_m = M::start("foo");
 //!         follows_m.OutP(Predicate("follows"));
+//! let follows_m = follows_m.compile();
 //! graph.find(V::start(Node("C"))
 //!              .Follow(&follows_m)
 //!              .Has(Predicate("status"), Node("cool_person"))
@@ -83,10 +85,11 @@
 //!
 //! * [Graph](./graph/struct.Graph.html) provides `.find(<Query>)`;
 //! * [Vertex](./path/struct.Vertex.html) provides:
-//!     * [Path](./path/trait.Path.html) implemetation with `.Out(...)`, `.In(...)`, `.Has(...)`, `.Or(...)`, `.Follow(...)`, ...
-//!     * [Query](./path/trait.Query.html) implemetation with `.All()`, `.GetLimit(...)`, ...
+//!     * [Traverse](./path/trait.Traverse.html) implemetation with `.Out(...)`, `.In(...)`, `.Has(...)`, `.Or(...)`, `.Follow(...)`, ...
+//!     * finishing methods `.All()`, `.GetLimit(...)`, `.ToArray()`, ... returning a query
 //! * [Morphism](./path/struct.Morphism.html) provides:
-//!     * [Path](./path/trait.Path.html) implemetation with `.Out(...)`, `.In(...)`, `.Has(...)`, `.Or(...)`, `.Follow(...)`, ...
+//!     * [Traverse](./path/trait.Traverse.html) implemetation with `.Out(...)`, `.In(...)`, `.Has(...)`, `.Or(...)`, `.Follow(...)`, ...
+//!     * `.compile()` to use it in `.Follow(...)`
 //!
 //! Follow the links above for a complete lists of methods and to get more information
 //! about every mentioned structure.
@@ -105,25 +108,32 @@ extern crate log;
 
 pub mod graph;
 
-pub use graph::{Graph, Nodes};
+pub use graph::{Graph, Nodes, Shape};
 pub use graph::APIVersion::{V1, DefaultVersion};
 
 mod selector;
 
 pub mod error;
 pub mod path;
+pub mod quad;
+
+pub use quad::Quad;
 
 pub use error::Result;
 pub use error::Error;
 
 pub mod selectors {
+    pub use selector::{NodeSelector, PredicateSelector, TagSelector};
     pub use selector::NodeSelector::*;
     pub use selector::PredicateSelector::*;
     pub use selector::TagSelector::*;
 }
 
 pub mod paths {
-    pub use path::{Trail, Vertex, Morphism};
+    pub use path::{Trail, Vertex, Morphism, Traverse};
     pub use path::Traversal::*;
     pub use path::Final::*;
+    // `Comparison::Regex` is left qualified, not to clash with `selectors::Regex`
+    pub use path::Comparison;
+    pub use path::Comparison::{Gt, Lt, Gte, Lte};
 }
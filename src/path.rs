@@ -177,6 +177,13 @@
 /// graph.find(vertex![ AnyNode -> And(&(cFollows + path![ Or(&dFollows) ])) => All ]).unwrap();
 /// ```
 ///
+/// A compiled Morphism may be extended the same way, it keeps its name:
+///
+/// ```ignore
+/// let fof = morphism![ "fof" -> OutP(Predicate("follows")) -> OutP(Predicate("follows")) ];
+/// let cool_fof = fof + path![ Has(Predicate("status"), Node("cool_person")) ];
+/// ```
+///
 /// `path!` macro syntax:
 ///
 /// ```text
@@ -193,13 +200,16 @@
 /// See [Traversal](../path/enum/Traversal.html) for a full list of supported traversals.
 
 use std::ops::Add;
+use std::mem;
 
 use selector::{NodeSelector, TagSelector, PredicateSelector};
 
-use selector::NodeSelector::{AnyNode, Node, Nodes};
+use selector::NodeSelector::{AnyNode, Node, Nodes, Float, Decimal, NodeOwned, NodesOwned, Iri, Literal};
 use selector::TagSelector::{AnyTag, Tag, Tags};
 use selector::PredicateSelector::{AnyPredicate, Predicate, Predicates};
 use selector::PredicateSelector::Route as FromRoute;
+use selector::PredicateSelector::Not as NotPredicates;
+use selector::PredicateSelector::Regex as MatchingPredicates;
 
 #[macro_export]
 macro_rules! vertex(
@@ -241,6 +251,8 @@ macro_rules! path(
 /// Represents a traversal part of a path. Used to contruct both Paths and Queries.
 pub enum Traversal<'t> {
     // Basic Trail
+    // the tags of `Out`, `In` and `Both` are set to the predicate each node was reached
+    // through, so with `Predicates(..)` or `Route(..)` you know which one was followed
     Out(PredicateSelector<'t>, TagSelector<'t>),
     OutP(PredicateSelector<'t>),
     OutT(TagSelector<'t>),
@@ -250,54 +262,117 @@ pub enum Traversal<'t> {
     Both(PredicateSelector<'t>, TagSelector<'t>),
     BothP(PredicateSelector<'t>),
     BothT(TagSelector<'t>),
+    // `Both` passes a node twice if it is reached in both directions, i.e. for a symmetric
+    // relationship stored both ways; `BothVia` passes every such node only once
+    BothVia(PredicateSelector<'t>),
     Is(NodeSelector<'t>),
+    // a step with a node filter applied to where it lands, i.e. `.Out(route).Is(node)`
+    OutIs(PredicateSelector<'t>, NodeSelector<'t>),
+    InIs(PredicateSelector<'t>, NodeSelector<'t>),
     Has(PredicateSelector<'t>, NodeSelector<'t>),
+    // same as `Has`, but for the incoming links: keeps the nodes the given nodes link to
+    HasR(PredicateSelector<'t>, NodeSelector<'t>),
+    // keeps the nodes linked with the given nodes in any direction, rendered as the `Or` of
+    // `Has` and `HasR`, which repeats the whole path before it: so it is only allowed in
+    // a Vertex, which has a start node, but not in a Morphism or a `path!`
+    HasBoth(PredicateSelector<'t>, NodeSelector<'t>),
+    // from the nodes to the predicates of their incoming or outgoing links
+    InPredicates,
+    OutPredicates,
     // Tagging
     TagWith(TagSelector<'t>),
     As(TagSelector<'t>),
     Back(TagSelector<'t>),
     Save(PredicateSelector<'t>, TagSelector<'t>),
+    // unlike `Save`, keeps the nodes which have no such predicate, just with no tag set for them
+    SaveOpt(PredicateSelector<'t>, TagSelector<'t>),
     // Joining
     Intersect(&'t CompiledRoute),
     And(&'t CompiledRoute),
     Union(&'t CompiledRoute),
     Or(&'t CompiledRoute),
+    Except(&'t CompiledRoute),
+    // passes every node only once, i.e. after a `Union` of the paths reaching the same nodes
+    Unique,
+    // Labels
+    // following traversals only go through the quads with the given labels (subgraphs)
+    LabelContext(PredicateSelector<'t>, TagSelector<'t>),
+    LabelContextP(PredicateSelector<'t>),
+    // from the nodes to the labels of the quads they are in
+    Labels,
+    // Filtering
+    // keeps only the nodes which values pass the comparison, i.e. `.Filter(gt("2000"))`
+    Filter(Comparison<'t>),
+    // Paging
+    // NB: both are applied to the whole flow of nodes passing through them, so inside
+    // a Morphism they don't limit the nodes per every starting node, but per query
+    Skip(i32),
+    Limit(i32),
     // Morphisms
     Follow(&'t CompiledReuse),
     FollowR(&'t CompiledReuse)
 }
 
+/// A comparison the values of the nodes are filtered with, see `Traversal::Filter`
+pub enum Comparison<'c> {
+    Gt(&'c str),
+    Lt(&'c str),
+    Gte(&'c str),
+    Lte(&'c str),
+    Regex(&'c str)
+}
+
 /// Represents a final part of a path. Used to contruct Queries.
+#[derive(Copy, Debug, Clone, PartialEq)]
 pub enum Final {
     Undefined,
     All,
     GetLimit(i32),
+    /// Same as `GetLimit(n)`
+    FirstN(i32),
+    /// Same as `GetLimit(1)`, for `Graph::find_one`
+    First,
     ToArray,
     ToValue,
     TagArray,
-    TagValue
-    /* ForEach(|int|:'q -> int) */
+    TagValue,
+    Count,
+    /// A body of JavaScript function called for every node `d`, i.e. `"g.Emit(d.id)"`,
+    /// rendered as `.ForEach(function(d){ <body> })`, the result is what it emits;
+    /// the body is `'static`, so `Final` stays `Copy`. A body with unbalanced quotes,
+    /// parentheses or brackets is rejected, the query is not compiled then
+    ForEach(&'static str)
     /* Map(|int|:'q -> int) */
 }
 
-/// This enum defines which type of a data this Query expects from Graph. Currently,
-/// Only `NodeSequence` is supported by Cayley for HTTP requests
-#[derive(Debug)]
+/// This enum defines which type of a data this Query expects from Graph. `NodeSequence`
+/// is what `Graph::find` handles, `SingleNode` is handled by `Graph::find_one`, `NameSequence`
+/// by `Graph::find_names`, `TagSequence` and `SingleTag` by `Graph::find_tags` and `Graph::find_tag`,
+/// `CountValue` by `Graph::count`, `EmittedSequence` by `Graph::find_emitted`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Expectation {
     Unknown,
     SingleNode,
     NodeSequence,
     NameSequence,
     TagSequence,
-    SingleTag
+    SingleTag,
+    CountValue,
+    EmittedSequence
 }
 
 // ================================ Path, Query & Reuse ===================== //
 
+/// The name Cayley binds the graph to, the paths start with it, i.e. `g.V()` or `g.M()`,
+/// unless compiled for another name with the `_at` methods, i.e. `Vertex::compile_query_at`
+pub const DEFAULT_ROOT_NAME: &'static str = "g";
+
 /// Represents a navigational part of a path, i.e. `.Out("foo").Intersect(bar).Has("buz")`
 trait Path: ToString {
 
-    fn compile_path(&self) -> Option<CompiledPath>;
+    fn compile_path(&self) -> Option<CompiledPath> { self.compile_path_at(DEFAULT_ROOT_NAME) }
+
+    fn compile_path_at(&self, root_name: &str) -> Option<CompiledPath>;
 
 }
 
@@ -306,14 +381,18 @@ trait Path: ToString {
 /// `g.M().Out("foo").Intersect(bar).Has("buz")`
 trait Route: Path {
 
-    fn compile_route(&self) -> Option<CompiledRoute>;
+    fn compile_route(&self) -> Option<CompiledRoute> { self.compile_route_at(DEFAULT_ROOT_NAME) }
+
+    fn compile_route_at(&self, root_name: &str) -> Option<CompiledRoute>;
 
 }
 
 /// Represents a query, i.e. `g.V().Out("foo").Intersect(bar).Has("buz").GetLimit(10)`
 trait Query: Route {
 
-    fn compile_query(&self) -> Option<CompiledQuery>;
+    fn compile_query(&self) -> Option<CompiledQuery> { self.compile_query_at(DEFAULT_ROOT_NAME) }
+
+    fn compile_query_at(&self, root_name: &str) -> Option<CompiledQuery>;
 
 }
 
@@ -322,7 +401,9 @@ trait Reuse: Route {
 
     fn get_name(&self) -> &str;
 
-    fn compile_reuse(&self) -> Option<CompiledReuse>;
+    fn compile_reuse(&self) -> Option<CompiledReuse> { self.compile_reuse_at(DEFAULT_ROOT_NAME) }
+
+    fn compile_reuse_at(&self, root_name: &str) -> Option<CompiledReuse>;
 
 }
 
@@ -347,6 +428,57 @@ pub struct CompiledQuery {
     pub expectation: Expectation
 }
 
+/// A compiled query split into its parts, see `CompiledQuery::components`, i.e. for
+/// `var m = g.M().Out("foo");g.V("bar").Follow(m).Has("buz").GetLimit(10)`:
+///
+/// * `prefix`: `var m = g.M().Out("foo");`
+/// * `root`: `g.V("bar")`
+/// * `traversals`: `.Follow(m).Has("buz")`
+/// * `_final`: `.GetLimit(10)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryComponents {
+    pub prefix: String,
+    pub root: String,
+    pub traversals: String,
+    pub _final: String
+}
+
+impl CompiledQuery {
+
+    /// Get the parts this query consists of, split from its current `prefix` and `value`
+    pub fn components(&self) -> QueryComponents {
+        let calls = split_calls(self.value.as_str());
+        let root = calls.first().map_or("", |root| *root);
+        let _final = match calls.last() {
+            Some(last) if calls.len() > 1 && is_final_call(last) => *last,
+            _ => ""
+        };
+        QueryComponents {
+            prefix: self.prefix.clone(),
+            root: root.to_string(),
+            traversals: self.value[root.len()..self.value.len() - _final.len()].to_string(),
+            _final: _final.to_string()
+        }
+    }
+
+    /// The query as a standalone Gremlin script: `prefix` followed by `value`. A `Graph`
+    /// may send more, i.e. declare saved Morphisms before,
+    /// see `Graph::to_gremlin` for what exactly is sent
+    pub fn to_gremlin(&self) -> String {
+        self.prefix.clone() + &self.value
+    }
+
+}
+
+impl CompiledRoute {
+
+    /// The route as a standalone Gremlin expression: `prefix` followed by `value`
+    pub fn to_gremlin(&self) -> String {
+        self.prefix.clone() + &self.value
+    }
+
+}
+
 /// Stores a named path, i.e. `out_int_has = g.M().Out("foo").Intersect(bar).Has("buz")`
 pub struct CompiledReuse {
     pub prefix: String,
@@ -354,6 +486,26 @@ pub struct CompiledReuse {
     pub value: String
 }
 
+impl CompiledReuse {
+
+    /// The declaration of this named path as it is put before the queries using it,
+    /// with the declarations of the named paths it uses: `prefix` followed by `var name = value;`
+    pub fn to_gremlin(&self) -> String {
+        format!("{prefix}var {name} = {path};", prefix = self.prefix, name = self.name, path = self.value)
+    }
+
+}
+
+/// Two named paths are the same if both their names and their paths are the same,
+/// so they may be declared only once when used together
+impl PartialEq for CompiledReuse {
+
+    fn eq(&self, other: &CompiledReuse) -> bool {
+        self.name == other.name && self.value == other.value
+    }
+
+}
+
 impl Add for CompiledPath {
     type Output = CompiledPath;
 
@@ -372,6 +524,105 @@ impl Add<CompiledPath> for CompiledRoute {
 
 }
 
+/// Extends a named path with more traversals, the name stays the same
+impl Add<CompiledPath> for CompiledReuse {
+    type Output = CompiledReuse;
+
+    fn add(self, _rhs: CompiledPath) -> CompiledReuse {
+        CompiledReuse { prefix: _rhs.prefix + &self.prefix, name: self.name, value: self.value + &_rhs.value }
+    }
+
+}
+
+/// Adds [Traversal](./enum.Traversal.html)s to a path one by one, an alternative to the
+/// `vertex!` and `morphism!` macros, i.e. for the code generated by other tools. Every
+/// method adds the Traversal with the same name:
+///
+/// ```ignore
+/// use cayley::path::{Vertex, Morphism, Traverse}; // Traverse trait import is required
+///
+/// let friend_of_friend = Morphism::start("friendOfFriend").OutP(Predicate("follows"))
+///                                                         .OutP(Predicate("follows")).compile();
+/// let mut cool = Vertex::start(Node("C"));
+///         cool.Follow(&friend_of_friend);
+///         cool.Has(Predicate("status"), Node("cool_person"));
+/// ```
+#[allow(non_snake_case)]
+pub trait Traverse<'t> {
+
+    fn traverse(&mut self, traversal: Traversal<'t>) -> &mut Self;
+
+    // Basic Trail
+    fn Out(&mut self, predicates: PredicateSelector<'t>, tags: TagSelector<'t>) -> &mut Self { self.traverse(Traversal::Out(predicates, tags)) }
+    fn OutP(&mut self, predicates: PredicateSelector<'t>) -> &mut Self { self.traverse(Traversal::OutP(predicates)) }
+    fn OutT(&mut self, tags: TagSelector<'t>) -> &mut Self { self.traverse(Traversal::OutT(tags)) }
+    fn In(&mut self, predicates: PredicateSelector<'t>, tags: TagSelector<'t>) -> &mut Self { self.traverse(Traversal::In(predicates, tags)) }
+    fn InP(&mut self, predicates: PredicateSelector<'t>) -> &mut Self { self.traverse(Traversal::InP(predicates)) }
+    fn InT(&mut self, tags: TagSelector<'t>) -> &mut Self { self.traverse(Traversal::InT(tags)) }
+    fn Both(&mut self, predicates: PredicateSelector<'t>, tags: TagSelector<'t>) -> &mut Self { self.traverse(Traversal::Both(predicates, tags)) }
+    fn BothP(&mut self, predicates: PredicateSelector<'t>) -> &mut Self { self.traverse(Traversal::BothP(predicates)) }
+    fn BothT(&mut self, tags: TagSelector<'t>) -> &mut Self { self.traverse(Traversal::BothT(tags)) }
+    fn BothVia(&mut self, predicates: PredicateSelector<'t>) -> &mut Self { self.traverse(Traversal::BothVia(predicates)) }
+    fn Is(&mut self, nodes: NodeSelector<'t>) -> &mut Self { self.traverse(Traversal::Is(nodes)) }
+    fn OutIs(&mut self, predicates: PredicateSelector<'t>, nodes: NodeSelector<'t>) -> &mut Self { self.traverse(Traversal::OutIs(predicates, nodes)) }
+    fn InIs(&mut self, predicates: PredicateSelector<'t>, nodes: NodeSelector<'t>) -> &mut Self { self.traverse(Traversal::InIs(predicates, nodes)) }
+    fn Has(&mut self, predicates: PredicateSelector<'t>, nodes: NodeSelector<'t>) -> &mut Self { self.traverse(Traversal::Has(predicates, nodes)) }
+    fn HasR(&mut self, predicates: PredicateSelector<'t>, nodes: NodeSelector<'t>) -> &mut Self { self.traverse(Traversal::HasR(predicates, nodes)) }
+    fn HasBoth(&mut self, predicates: PredicateSelector<'t>, nodes: NodeSelector<'t>) -> &mut Self { self.traverse(Traversal::HasBoth(predicates, nodes)) }
+    fn InPredicates(&mut self) -> &mut Self { self.traverse(Traversal::InPredicates) }
+    fn OutPredicates(&mut self) -> &mut Self { self.traverse(Traversal::OutPredicates) }
+
+    // Tagging
+    fn TagWith(&mut self, tags: TagSelector<'t>) -> &mut Self { self.traverse(Traversal::TagWith(tags)) }
+    fn As(&mut self, tags: TagSelector<'t>) -> &mut Self { self.traverse(Traversal::As(tags)) }
+    fn Back(&mut self, tags: TagSelector<'t>) -> &mut Self { self.traverse(Traversal::Back(tags)) }
+    fn Save(&mut self, predicates: PredicateSelector<'t>, tags: TagSelector<'t>) -> &mut Self { self.traverse(Traversal::Save(predicates, tags)) }
+    fn SaveOpt(&mut self, predicates: PredicateSelector<'t>, tags: TagSelector<'t>) -> &mut Self { self.traverse(Traversal::SaveOpt(predicates, tags)) }
+
+    // Joining
+    fn Intersect(&mut self, route: &'t CompiledRoute) -> &mut Self { self.traverse(Traversal::Intersect(route)) }
+    fn And(&mut self, route: &'t CompiledRoute) -> &mut Self { self.traverse(Traversal::And(route)) }
+    fn Union(&mut self, route: &'t CompiledRoute) -> &mut Self { self.traverse(Traversal::Union(route)) }
+    fn Or(&mut self, route: &'t CompiledRoute) -> &mut Self { self.traverse(Traversal::Or(route)) }
+    fn Except(&mut self, route: &'t CompiledRoute) -> &mut Self { self.traverse(Traversal::Except(route)) }
+    /// Keeps only the nodes of the path the given route doesn't reach, just another name
+    /// for `Except`: emits `.Except(<route>)`, i.e. `g.V("C").Out("follows").Except(g.V("D"))`
+    fn Without(&mut self, route: &'t CompiledRoute) -> &mut Self { self.traverse(Traversal::Except(route)) }
+    fn Unique(&mut self) -> &mut Self { self.traverse(Traversal::Unique) }
+
+    // Labels
+    fn LabelContext(&mut self, labels: PredicateSelector<'t>, tags: TagSelector<'t>) -> &mut Self { self.traverse(Traversal::LabelContext(labels, tags)) }
+    fn LabelContextP(&mut self, labels: PredicateSelector<'t>) -> &mut Self { self.traverse(Traversal::LabelContextP(labels)) }
+    fn Labels(&mut self) -> &mut Self { self.traverse(Traversal::Labels) }
+
+    // Filtering
+    fn Filter(&mut self, comparison: Comparison<'t>) -> &mut Self { self.traverse(Traversal::Filter(comparison)) }
+
+    // Paging
+    fn Skip(&mut self, n: i32) -> &mut Self { self.traverse(Traversal::Skip(n)) }
+    fn Limit(&mut self, n: i32) -> &mut Self { self.traverse(Traversal::Limit(n)) }
+
+    // Morphisms
+    fn Follow(&mut self, reusable: &'t CompiledReuse) -> &mut Self { self.traverse(Traversal::Follow(reusable)) }
+    fn FollowR(&mut self, reusable: &'t CompiledReuse) -> &mut Self { self.traverse(Traversal::FollowR(reusable)) }
+
+    // Optional: the traversal is added only if there is some value, with `None` nothing
+    // is added at all, i.e. `Vertex::start(AnyNode).maybe_has(Predicate("status"), status).All()`
+    fn maybe_has(&mut self, predicates: PredicateSelector<'t>, node: Option<&'t str>) -> &mut Self {
+        match node { Some(node) => self.Has(predicates, Node(node)), None => self }
+    }
+    fn maybe_out(&mut self, predicate: Option<&'t str>) -> &mut Self {
+        match predicate { Some(predicate) => self.OutP(Predicate(predicate)), None => self }
+    }
+    fn maybe_in(&mut self, predicate: Option<&'t str>) -> &mut Self {
+        match predicate { Some(predicate) => self.InP(Predicate(predicate)), None => self }
+    }
+    fn maybe_is(&mut self, node: Option<&'t str>) -> &mut Self {
+        match node { Some(node) => self.Is(Node(node)), None => self }
+    }
+
+}
+
 // ================================ Trail ============================= //
 
 /// A structure to hold [Path](../path/trait.Path.html) data before its compilation to
@@ -384,6 +635,11 @@ impl<'t> Trail<'t> {
         Trail(traversals).compile_path()
     }
 
+    /// Same as `compile_path`, but the routes inside start with `root_name` instead of `g`
+    pub fn compile_path_at<'a>(root_name: &str, traversals: Box<[Traversal<'a>]>) -> Option<CompiledPath> {
+        Trail(traversals).compile_path_at(root_name)
+    }
+
 }
 
 impl<'ts> ToString for Trail<'ts> {
@@ -399,13 +655,14 @@ impl<'ts> ToString for Trail<'ts> {
 
 impl<'p> Path for Trail<'p> {
 
-    fn compile_path(&self) -> Option<CompiledPath> {
+    fn compile_path_at(&self, root_name: &str) -> Option<CompiledPath> {
         match *self {
-            Trail(ref traversals) =>
-                Some(CompiledPath {
-                    prefix: parse_prefix(traversals),
-                    value: parse_traversals(traversals)
-                })
+            Trail(ref traversals) if check_traversals(traversals) && !needs_root(traversals) =>
+                parse_prefix(traversals).map(|prefix| CompiledPath {
+                    prefix: prefix,
+                    value: parse_traversals(root_name, traversals)
+                }),
+            _ => None
         }
     }
 
@@ -423,6 +680,34 @@ impl<'m> Morphism<'m> {
         Morphism(name, traversals).compile_reuse()
     }
 
+    /// Same as `compile_reuse`, but compiled as `<root_name>.M()` instead of `g.M()`
+    pub fn compile_reuse_at<'a>(root_name: &str, name: &'a str, traversals: Box<[Traversal<'a>]>) -> Option<CompiledReuse> {
+        Morphism(name, traversals).compile_reuse_at(root_name)
+    }
+
+    /// Start a Morphism named `name` with no traversals, to add them with
+    /// [Traverse](./trait.Traverse.html) methods
+    pub fn start(name: &'m str) -> Morphism<'m> {
+        Morphism(name, Vec::new().into_boxed_slice())
+    }
+
+    /// Compile a Morphism built with [Traverse](./trait.Traverse.html) methods,
+    /// panics if it fails to compile, same as `morphism!` macro does
+    pub fn compile(&self) -> CompiledReuse {
+        match self.compile_reuse() {
+            Some(m) => m, None => panic!("Morphism path failed to compile!")
+        }
+    }
+
+}
+
+impl<'m> Traverse<'m> for Morphism<'m> {
+
+    fn traverse(&mut self, traversal: Traversal<'m>) -> &mut Morphism<'m> {
+        push_traversal(&mut self.1, traversal);
+        self
+    }
+
 }
 
 impl<'ts> ToString for Morphism<'ts> {
@@ -440,13 +725,14 @@ impl<'ts> ToString for Morphism<'ts> {
 
 impl<'p> Path for Morphism<'p> {
 
-    fn compile_path(&self) -> Option<CompiledPath> {
+    fn compile_path_at(&self, root_name: &str) -> Option<CompiledPath> {
         match *self {
-            Morphism(_, ref traversals) =>
-                Some(CompiledPath {
-                    prefix: parse_prefix(traversals),
-                    value: parse_traversals(traversals)
-                })
+            Morphism(_, ref traversals) if check_traversals(traversals) && !needs_root(traversals) =>
+                parse_prefix(traversals).map(|prefix| CompiledPath {
+                    prefix: prefix,
+                    value: parse_traversals(root_name, traversals)
+                }),
+            _ => None
         }
     }
 
@@ -454,13 +740,14 @@ impl<'p> Path for Morphism<'p> {
 
 impl<'r> Route for Morphism<'r> {
 
-    fn compile_route(&self) -> Option<CompiledRoute> {
+    fn compile_route_at(&self, root_name: &str) -> Option<CompiledRoute> {
         match *self {
-            Morphism(_, ref traversals) =>
-                Some(CompiledRoute {
-                    prefix: parse_prefix(traversals),
-                    value: "g.M()".to_string() + &parse_traversals(traversals)
-                })
+            Morphism(_, ref traversals) if check_traversals(traversals) && !needs_root(traversals) =>
+                parse_prefix(traversals).map(|prefix| CompiledRoute {
+                    prefix: prefix,
+                    value: format!("{}.M()", root_name) + &parse_traversals(root_name, traversals)
+                }),
+            _ => None
         }
     }
 
@@ -474,14 +761,15 @@ impl<'r> Reuse for Morphism<'r> {
         }
     }
 
-    fn compile_reuse(&self) -> Option<CompiledReuse> {
+    fn compile_reuse_at(&self, root_name: &str) -> Option<CompiledReuse> {
         match *self {
-            Morphism(name, ref traversals) =>
-                Some(CompiledReuse {
+            Morphism(name, ref traversals) if check_traversals(traversals) && !needs_root(traversals) =>
+                parse_prefix(traversals).map(|prefix| CompiledReuse {
                     name: name.to_string(),
-                    prefix: parse_prefix(traversals),
-                    value: "g.M()".to_string() + &parse_traversals(traversals)
-                })
+                    prefix: prefix,
+                    value: format!("{}.M()", root_name) + &parse_traversals(root_name, traversals)
+                }),
+            _ => None
         }
     }
 
@@ -503,6 +791,93 @@ impl<'v> Vertex<'v> {
         Vertex(start, traversals, Final::Undefined).compile_route()
     }
 
+    /// Same as `compile_query`, but compiled as `<root_name>.V(..)` instead of `g.V(..)`,
+    /// for Cayley binding the graph to another name, see `Graph::with_root_name`
+    pub fn compile_query_at<'a>(root_name: &str, start: NodeSelector<'a>, traversals: Box<[Traversal<'a>]>, _final: Final)
+                                -> Option<CompiledQuery> {
+        Vertex(start, traversals, _final).compile_query_at(root_name)
+    }
+
+    /// Same as `compile_route`, but compiled as `<root_name>.V(..)` instead of `g.V(..)`
+    pub fn compile_route_at<'a>(root_name: &str, start: NodeSelector<'a>, traversals: Box<[Traversal<'a>]>)
+                                -> Option<CompiledRoute> {
+        Vertex(start, traversals, Final::Undefined).compile_route_at(root_name)
+    }
+
+    /// Start a Vertex from `start` nodes with no traversals, to add them with
+    /// [Traverse](./trait.Traverse.html) methods
+    pub fn start(start: NodeSelector<'v>) -> Vertex<'v> {
+        Vertex(start, Vec::new().into_boxed_slice(), Final::Undefined)
+    }
+
+    /// Compile a Vertex built with [Traverse](./trait.Traverse.html) methods into a route
+    /// to use in other queries, panics if it fails to compile, same as `vertex!` macro does
+    pub fn compile(&self) -> CompiledRoute {
+        match self.compile_route() {
+            Some(v) => v, None => panic!("Vertex query failed to compile!")
+        }
+    }
+
+    // The methods below finish a Vertex built with [Traverse](./trait.Traverse.html) methods
+    // with the Final of the same name and compile it into a query, they panic if it fails
+    // to compile, same as `vertex!` macro does
+
+    /// Finish with `.All()`, to execute with `Graph::find`
+    #[allow(non_snake_case)]
+    pub fn All(&mut self) -> CompiledQuery { self.finish(Final::All) }
+
+    /// Finish with `.GetLimit(limit)`, to execute with `Graph::find`
+    #[allow(non_snake_case)]
+    pub fn GetLimit(&mut self, limit: i32) -> CompiledQuery { self.finish(Final::GetLimit(limit)) }
+
+    /// Finish with `.GetLimit(1)`, to execute with `Graph::find_one`
+    #[allow(non_snake_case)]
+    pub fn First(&mut self) -> CompiledQuery { self.finish(Final::First) }
+
+    /// Finish with `.GetLimit(n)`, to execute with `Graph::find`
+    #[allow(non_snake_case)]
+    pub fn FirstN(&mut self, n: i32) -> CompiledQuery { self.finish(Final::FirstN(n)) }
+
+    /// Finish with `.ToArray()`, to execute with `Graph::find_names`
+    #[allow(non_snake_case)]
+    pub fn ToArray(&mut self) -> CompiledQuery { self.finish(Final::ToArray) }
+
+    /// Finish with `.ToValue()`, to execute with `Graph::find_one`
+    #[allow(non_snake_case)]
+    pub fn ToValue(&mut self) -> CompiledQuery { self.finish(Final::ToValue) }
+
+    /// Finish with `.TagArray()`, to execute with `Graph::find_tags`
+    #[allow(non_snake_case)]
+    pub fn TagArray(&mut self) -> CompiledQuery { self.finish(Final::TagArray) }
+
+    /// Finish with `.TagValue()`, to execute with `Graph::find_tag`
+    #[allow(non_snake_case)]
+    pub fn TagValue(&mut self) -> CompiledQuery { self.finish(Final::TagValue) }
+
+    /// Finish with `.Count()`, to execute with `Graph::count`
+    #[allow(non_snake_case)]
+    pub fn Count(&mut self) -> CompiledQuery { self.finish(Final::Count) }
+
+    /// Finish with `.ForEach(function(d){ <body> })`, to execute with `Graph::find_emitted`
+    #[allow(non_snake_case)]
+    pub fn ForEach(&mut self, body: &'static str) -> CompiledQuery { self.finish(Final::ForEach(body)) }
+
+    fn finish(&mut self, _final: Final) -> CompiledQuery {
+        self.2 = _final;
+        match self.compile_query() {
+            Some(v) => v, None => panic!("Vertex query failed to compile!")
+        }
+    }
+
+}
+
+impl<'v> Traverse<'v> for Vertex<'v> {
+
+    fn traverse(&mut self, traversal: Traversal<'v>) -> &mut Vertex<'v> {
+        push_traversal(&mut self.1, traversal);
+        self
+    }
+
 }
 
 impl<'ts> ToString for Vertex<'ts> {
@@ -519,13 +894,14 @@ impl<'ts> ToString for Vertex<'ts> {
 
 impl<'p> Path for Vertex<'p> {
 
-    fn compile_path(&self) -> Option<CompiledPath> {
+    fn compile_path_at(&self, root_name: &str) -> Option<CompiledPath> {
         match *self {
-            Vertex(_, ref traversals, _) =>
-                Some(CompiledPath {
-                    prefix: parse_prefix(traversals),
-                    value: parse_traversals(traversals)
-                })
+            Vertex(_, ref traversals, _) if check_traversals(traversals) && !needs_root(traversals) =>
+                parse_prefix(traversals).map(|prefix| CompiledPath {
+                    prefix: prefix,
+                    value: parse_traversals(root_name, traversals)
+                }),
+            _ => None
         }
     }
 
@@ -533,13 +909,16 @@ impl<'p> Path for Vertex<'p> {
 
 impl<'r> Route for Vertex<'r> {
 
-    fn compile_route(&self) -> Option<CompiledRoute> {
+    fn compile_route_at(&self, root_name: &str) -> Option<CompiledRoute> {
         match *self {
-            Vertex(ref start, ref traversals, _) =>
-                Some(CompiledRoute {
-                    prefix: parse_prefix(traversals),
-                    value: parse_start(start) + &parse_traversals(traversals)
-                })
+            Vertex(ref start, ref traversals, _) if check_traversals(traversals) =>
+                parse_prefix(traversals).map(|prefix| CompiledRoute {
+                    prefix: prefix,
+                    value: { let root = parse_start(root_name, start);
+                             let traversals = parse_traversals_from(root_name, Some(&root), traversals);
+                             root + &traversals }
+                }),
+            _ => None
         }
     }
 
@@ -547,17 +926,23 @@ impl<'r> Route for Vertex<'r> {
 
 impl<'q> Query for Vertex<'q> {
 
-    fn compile_query(&self) -> Option<CompiledQuery> {
+    fn compile_query_at(&self, root_name: &str) -> Option<CompiledQuery> {
         match *self {
+            Vertex(_, ref traversals, _) if !check_traversals(traversals) => None,
+            // Cayley handles non-positive limits inconsistently
+            Vertex(_, _, Final::GetLimit(limit)) | Vertex(_, _, Final::FirstN(limit)) if limit <= 0 => None,
+            // an unbalanced body could close the `function(d){` early and run outside of it
+            Vertex(_, _, Final::ForEach(body)) if validate_query(body).is_err() => None,
             Vertex(ref start, ref traversals, ref _final) => {
-                let prefix = parse_prefix(traversals);
+                let prefix = match parse_prefix(traversals) {
+                    Some(prefix) => prefix,
+                    None => return None
+                };
+                let root = parse_start(root_name, start);
                 let mut value = String::new();
-                value.push_str(&parse_start(start));
-                value.push_str(&parse_traversals(traversals));
-                match _final {
-                    &Final::Undefined => {},
-                    _ => value.push_str(&parse_final(_final))
-                }
+                value.push_str(&root);
+                value.push_str(&parse_traversals_from(root_name, Some(&root), traversals));
+                value.push_str(&parse_final(_final));
                 Some(CompiledQuery {
                     prefix: prefix,
                     value: value,
@@ -565,10 +950,14 @@ impl<'q> Query for Vertex<'q> {
                         &Final::Undefined    => Expectation::Unknown,
                         &Final::All          => Expectation::NodeSequence,
                         &Final::GetLimit(..) => Expectation::NodeSequence,
+                        &Final::FirstN(..)   => Expectation::NodeSequence,
+                        &Final::First        => Expectation::NodeSequence,
                         &Final::ToArray      => Expectation::NameSequence,
                         &Final::ToValue      => Expectation::SingleNode,
                         &Final::TagArray     => Expectation::TagSequence,
-                        &Final::TagValue     => Expectation::SingleTag
+                        &Final::TagValue     => Expectation::SingleTag,
+                        &Final::Count        => Expectation::CountValue,
+                        &Final::ForEach(..)  => Expectation::EmittedSequence
                     }
                 })
             }
@@ -577,149 +966,481 @@ impl<'q> Query for Vertex<'q> {
 
 }
 
+// split a compiled path into its calls on the top level, i.e. `g.V("a")`, `.Out("b")`, `.All()`,
+// dots, parentheses and brackets inside the quoted names or inside the calls don't count
+fn split_calls(value: &str) -> Vec<&str> {
+    let mut calls = Vec::new();
+    let mut start = 0;
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut closed = false;
+    for (position, symbol) in value.char_indices() {
+        if escaped { escaped = false; continue }
+        if quoted {
+            match symbol { '\\' => escaped = true, '"' => quoted = false, _ => {} };
+            continue
+        }
+        match symbol {
+            '"' => quoted = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => { depth -= 1; closed = depth == 0; continue },
+            '.' if depth == 0 && closed => { calls.push(&value[start..position]); start = position; },
+            _ => {}
+        };
+        closed = false;
+    }
+    if start < value.len() { calls.push(&value[start..]); }
+    calls
+}
+
+fn is_final_call(call: &str) -> bool {
+    ["All", "GetLimit", "ToArray", "ToValue", "TagArray", "TagValue", "Count", "ForEach"].iter()
+        .any(|name| call.starts_with(&format!(".{}(", name)))
+}
+
+// a path keeps its traversals in a boxed slice, so a traversal is added by rebuilding it
+fn push_traversal<'t>(traversals: &mut Box<[Traversal<'t>]>, traversal: Traversal<'t>) {
+    let mut extended = mem::replace(traversals, Vec::new().into_boxed_slice()).into_vec();
+    extended.push(traversal);
+    *traversals = extended.into_boxed_slice();
+}
+
+// ================================ validation ============================== //
+
+/// Check that quotes, parentheses and brackets in a compiled query string are
+/// balanced, if they are not, returns the position where the problem was found.
+/// This is not a Gremlin parser, just a guard against broken query strings.
+pub fn validate_query(query: &str) -> Result<(), usize> {
+    let mut opened: Vec<(char, usize)> = Vec::new();
+    let mut quote: Option<(char, usize)> = None;
+    let mut escaped = false;
+    for (position, symbol) in query.char_indices() {
+        match quote {
+            Some((quote_symbol, _)) => {
+                if escaped { escaped = false; }
+                else if symbol == '\\' { escaped = true; }
+                else if symbol == quote_symbol { quote = None; }
+            },
+            None => match symbol {
+                '"' | '\'' => quote = Some((symbol, position)),
+                '(' | '[' | '{' => opened.push((symbol, position)),
+                ')' | ']' | '}' => match opened.pop() {
+                    Some(('(', _)) if symbol == ')' => {},
+                    Some(('[', _)) if symbol == ']' => {},
+                    Some(('{', _)) if symbol == '}' => {},
+                    _ => return Err(position)
+                },
+                _ => {}
+            }
+        }
+    }
+    match (quote, opened.pop()) {
+        (Some((_, position)), _) | (None, Some((_, position))) => Err(position),
+        (None, None) => Ok(())
+    }
+}
+
 // ================================ parsing ================================= //
 
-fn parse_prefix(traversals: &Box<[Traversal]>) -> String {
-    let mut result = String::new();
+// `Save` and `SaveOpt` put a value of a single predicate under a single tag,
+// so a path with these having any other selectors can't be compiled
+fn check_traversals(traversals: &Box<[Traversal]>) -> bool {
+    traversals.iter().all(|traversal| match *traversal {
+        Traversal::Save(ref predicates, ref tags) |
+        Traversal::SaveOpt(ref predicates, ref tags) => match (predicates, tags) {
+            (&Predicate(_), &Tag(_)) => true,
+            _ => false
+        },
+        _ => true
+    })
+}
+
+// some traversals repeat the whole path before them, so they need its start node
+fn needs_root(traversals: &Box<[Traversal]>) -> bool {
+    traversals.iter().any(|traversal| match *traversal {
+        Traversal::HasBoth(..) => true,
+        _ => false
+    })
+}
+
+// the declarations of all the Morphisms a path uses, including the ones used by them,
+// every Morphism is declared once, even if it is followed several times; if two
+// different Morphisms have the same name, the path can't be compiled
+fn parse_prefix(traversals: &Box<[Traversal]>) -> Option<String> {
+    let mut used: Vec<String> = Vec::new();
     for traversal in traversals.iter() {
-        match *traversal {
-            Traversal::Follow(reusable) | Traversal::FollowR(reusable) => {
-                result.push_str(&reusable.prefix);
-                result.push_str(format!("var {name} = {path};",
-                                        name = reusable.name,
-                                        path = reusable.value).as_str());
-            },
-            Traversal::Intersect(query) | Traversal::And(query) | Traversal::Union(query) | Traversal::Or(query) => {
-                result.push_str(&query.prefix);
-            },
-            _ => {}
+        used.push(match *traversal {
+            Traversal::Follow(reusable) | Traversal::FollowR(reusable) =>
+                format!("{prefix}var {name} = {path};", prefix = reusable.prefix,
+                                                        name = reusable.name,
+                                                        path = reusable.value),
+            Traversal::Intersect(query) | Traversal::And(query) | Traversal::Union(query) | Traversal::Or(query) |
+            Traversal::Except(query) => query.prefix.clone(),
+            _ => continue
+        });
+    }
+    merge_prefixes(&borrow_names(&used))
+}
+
+/// Merge the prefixes of several compiled paths, i.e. to send them together, so
+/// every Morphism is declared once, even if several of the paths use it; if two
+/// different Morphisms have the same name, the prefixes can't be merged and it's `None`
+pub fn merge_prefixes(prefixes: &[&str]) -> Option<String> {
+    let mut declarations: Vec<String> = Vec::new();
+    for prefix in prefixes.iter() {
+        for declaration in split_declarations(prefix).into_iter() {
+            let same = match declarations.iter().find(|declared| declared_name(declared) == declared_name(&declaration)) {
+                Some(declared) => Some(*declared == declaration),
+                None => None
+            };
+            match same {
+                Some(false) => return None,
+                Some(true) => {},
+                None => declarations.push(declaration)
+            }
         }
     }
-    result
+    Some(declarations.concat())
+}
+
+// split a prefix into `var name = ...;` declarations, semicolons inside the quoted names don't count
+fn split_declarations(prefix: &str) -> Vec<String> {
+    let mut declarations = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for symbol in prefix.chars() {
+        current.push(symbol);
+        if escaped { escaped = false; }
+        else if quoted && symbol == '\\' { escaped = true; }
+        else if symbol == '"' { quoted = !quoted; }
+        else if !quoted && symbol == ';' {
+            declarations.push(current);
+            current = String::new();
+        }
+    }
+    declarations
+}
+
+fn declared_name(declaration: &str) -> &str {
+    declaration.trim_left_matches("var ").split(' ').next().unwrap_or("")
 }
 
-fn parse_start(start: &NodeSelector) -> String {
+fn parse_start(root_name: &str, start: &NodeSelector) -> String {
     match *start {
-        AnyNode => "g.V()".to_string(),
-        Node(name) => format!("g.V(\"{0}\")", name),
-        Nodes(ref names) => format!("g.V(\"{0}\")", names.connect("\",\""))
+        AnyNode => format!("{0}.V()", root_name),
+        Node(name) => format!("{0}.V(\"{1}\")", root_name, escape_js_string(name)),
+        Nodes(ref names) => format!("{0}.V(\"{1}\")", root_name, join_names(names)),
+        Float(..) | Decimal(..) => format!("{0}.V(\"{1}\")", root_name, parse_number(start)),
+        NodeOwned(ref name) => parse_start(root_name, &Node(name.as_str())),
+        NodesOwned(ref names) => parse_start(root_name, &Nodes(borrow_names(names))),
+        Iri(..) | Literal(..) => parse_start(root_name, &Node(parse_term(start).as_str()))
     }
 }
 
-fn parse_traversals(traversals: &Box<[Traversal]>) -> String {
+fn parse_traversals(root_name: &str, traversals: &Box<[Traversal]>) -> String {
+    parse_traversals_from(root_name, None, traversals)
+}
+
+// `root` is the start of a path, if it has one, the traversals repeating the path need it;
+// `root_name` is the name the graph is bound to, the predicates passed as a route start with it
+fn parse_traversals_from(root_name: &str, root: Option<&str>, traversals: &Box<[Traversal]>) -> String {
     let mut result = String::new();
     for traversal in traversals.iter() {
-        result.push_str(match *traversal {
+        let step = match *traversal {
             /* FIXME: Traversal:: shouldn't be required */
             // Basic Trail ================================================================================================
-            Traversal::Out(ref predicates, ref tags)   => format!(".Out({})",  parse_predicates_and_tags(predicates, tags)),
-            Traversal::OutP(ref predicates)            => format!(".Out({})",  parse_predicates_and_tags(predicates, &AnyTag)),
-            Traversal::OutT(ref tags)                  => format!(".Out({})",  parse_predicates_and_tags(&AnyPredicate, tags)),
-            Traversal::In(ref predicates, ref tags)    => format!(".In({})",   parse_predicates_and_tags(predicates, tags)),
-            Traversal::InP(ref predicates)             => format!(".In({})",   parse_predicates_and_tags(predicates, &AnyTag)),
-            Traversal::InT(ref tags)                   => format!(".In({})",   parse_predicates_and_tags(&AnyPredicate, tags)),
-            Traversal::Both(ref predicates, ref tags)  => format!(".Both({})", parse_predicates_and_tags(predicates, tags)),
-            Traversal::BothP(ref predicates)           => format!(".Both({})", parse_predicates_and_tags(predicates, &AnyTag)),
-            Traversal::BothT(ref tags)                 => format!(".Both({})", parse_predicates_and_tags(&AnyPredicate, tags)),
-            Traversal::Is(ref nodes)                   => match nodes {
-                                                              &AnyNode => ".Is()".to_string(),
-                                                              &Node(name) => format!(".Is(\"{}\")", name),
-                                                              &Nodes(ref names) => format!(".Is(\"{}\")", names.connect("\",\""))
+            Traversal::Out(ref predicates, ref tags)   => format!(".Out({})",  parse_predicates_and_tags(root_name, predicates, tags)),
+            Traversal::OutP(ref predicates)            => format!(".Out({})",  parse_predicates_and_tags(root_name, predicates, &AnyTag)),
+            Traversal::OutT(ref tags)                  => format!(".Out({})",  parse_predicates_and_tags(root_name, &AnyPredicate, tags)),
+            Traversal::In(ref predicates, ref tags)    => format!(".In({})",   parse_predicates_and_tags(root_name, predicates, tags)),
+            Traversal::InP(ref predicates)             => format!(".In({})",   parse_predicates_and_tags(root_name, predicates, &AnyTag)),
+            Traversal::InT(ref tags)                   => format!(".In({})",   parse_predicates_and_tags(root_name, &AnyPredicate, tags)),
+            Traversal::Both(ref predicates, ref tags)  => format!(".Both({})", parse_predicates_and_tags(root_name, predicates, tags)),
+            Traversal::BothP(ref predicates)           => format!(".Both({})", parse_predicates_and_tags(root_name, predicates, &AnyTag)),
+            Traversal::BothT(ref tags)                 => format!(".Both({})", parse_predicates_and_tags(root_name, &AnyPredicate, tags)),
+            Traversal::BothVia(ref predicates)         => format!(".Both({}).Unique()", parse_predicates_and_tags(root_name, predicates, &AnyTag)),
+            Traversal::Is(ref nodes)                   => parse_is(nodes),
+            Traversal::OutIs(ref predicates, ref nodes) => format!(".Out({}){}", parse_predicates_and_tags(root_name, predicates, &AnyTag), parse_is(nodes)),
+            Traversal::InIs(ref predicates, ref nodes) => format!(".In({}){}",  parse_predicates_and_tags(root_name, predicates, &AnyTag), parse_is(nodes)),
+            Traversal::Has(ref predicates, ref nodes)  => format!(".Has({})", parse_predicates_and_nodes(root_name, predicates, nodes)),
+            Traversal::HasR(ref predicates, ref nodes) => format!(".HasR({})", parse_predicates_and_nodes(root_name, predicates, nodes)),
+            Traversal::HasBoth(ref predicates, ref nodes) => match root {
+                                                              Some(root) => format!(".Has({0}).Or({1}{2}.HasR({0}))",
+                                                                                    parse_predicates_and_nodes(root_name, predicates, nodes),
+                                                                                    root, result),
+                                                              None => "".to_string()
                                                           },
-            Traversal::Has(ref predicates, ref nodes)  => format!(".Has({})", parse_predicates_and_nodes(predicates, nodes)),
+            Traversal::InPredicates                    => ".InPredicates()".to_string(),
+            Traversal::OutPredicates                   => ".OutPredicates()".to_string(),
             // Tagging =========================================================================================================
             Traversal::TagWith(ref tags) |
             Traversal::As(ref tags)                    => match tags {
                                                               &AnyTag => ".As()".to_string(),
-                                                              &Tag(name) => format!(".As(\"{}\")", name),
-                                                              &Tags(ref names) => format!(".As(\"{}\")", names.connect("\",\""))
+                                                              &Tag(name) => format!(".As(\"{}\")", escape_js_string(name)),
+                                                              &Tags(ref names) => format!(".As(\"{}\")", join_names(names))
                                                           },
             Traversal::Back(ref tags)                  => match tags {
                                                               &AnyTag => ".Back()".to_string(),
-                                                              &Tag(name) => format!(".Back(\"{}\")", name),
-                                                              &Tags(ref names) => format!(".Back(\"{}\")", names.connect("\",\""))
+                                                              &Tag(name) => format!(".Back(\"{}\")", escape_js_string(name)),
+                                                              &Tags(ref names) => format!(".Back(\"{}\")", join_names(names))
                                                           },
-            Traversal::Save(ref predicates, ref tags)  => format!(".Save({})", parse_predicates_and_tags(predicates, tags)),
+            Traversal::Save(ref predicates, ref tags)  => format!(".Save({})", parse_predicates_and_tags(root_name, predicates, tags)),
+            Traversal::SaveOpt(ref predicates, ref tags) => format!(".SaveOpt({})", parse_predicates_and_tags(root_name, predicates, tags)),
             // Joining =========================================================================================================
             Traversal::Intersect(query) |
             Traversal::And(query)                      => format!(".And({})", query.value),
             Traversal::Union(query) |
             Traversal::Or(query)                       => format!(".Or({})", query.value),
+            Traversal::Except(query)                   => format!(".Except({})", query.value),
+            Traversal::Unique                          => ".Unique()".to_string(),
+            // Labels ==========================================================================================================
+            Traversal::LabelContext(ref labels, ref tags) => format!(".LabelContext({})", parse_predicates_and_tags(root_name, labels, tags)),
+            Traversal::LabelContextP(ref labels)       => format!(".LabelContext({})", parse_predicates_and_tags(root_name, labels, &AnyTag)),
+            Traversal::Labels                          => ".Labels()".to_string(),
+            // Filtering =======================================================================================================
+            Traversal::Filter(ref comparison)          => format!(".Filter({})", parse_comparison(comparison)),
+            // Paging ==========================================================================================================
+            Traversal::Skip(n)                         => format!(".Skip({})", n),
+            Traversal::Limit(n)                        => format!(".Limit({})", n),
             // Morphisms =======================================================================================================
             Traversal::Follow(reusable)                => format!(".Follow({})", reusable.name),
             Traversal::FollowR(reusable)               => format!(".FollowR({})", reusable.name)
-        }.as_str());
+        };
+        result.push_str(step.as_str());
     }
     result
 }
 
+fn parse_comparison(comparison: &Comparison) -> String {
+    match *comparison {
+        Comparison::Gt(value)    => format!("gt(\"{}\")", escape_js_string(value)),
+        Comparison::Lt(value)    => format!("lt(\"{}\")", escape_js_string(value)),
+        Comparison::Gte(value)   => format!("gte(\"{}\")", escape_js_string(value)),
+        Comparison::Lte(value)   => format!("lte(\"{}\")", escape_js_string(value)),
+        Comparison::Regex(value) => format!("regex(\"{}\")", escape_js_string(value))
+    }
+}
+
+// join the names from a list selector to be put inside quotes: `foo","bar`;
+// with `canonical_selectors` feature, they are sorted and deduplicated before,
+// so the same set of names always produces the same query
+#[cfg(not(feature = "canonical_selectors"))]
+fn join_names(names: &[&str]) -> String {
+    escape_names(names).connect("\",\"")
+}
+
+#[cfg(feature = "canonical_selectors")]
+fn join_names(names: &[&str]) -> String {
+    let mut names = names.to_vec();
+    names.sort();
+    names.dedup();
+    escape_names(&names).connect("\",\"")
+}
+
+fn escape_names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| escape_js_string(name)).collect()
+}
+
+// names are put inside double-quoted JavaScript strings, so quotes, backslashes
+// and control characters in them have to be escaped
+fn escape_js_string(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for symbol in name.chars() {
+        match symbol {
+            '"'  => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{2028}' => escaped.push_str("\\u2028"),
+            '\u{2029}' => escaped.push_str("\\u2029"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+fn borrow_names(names: &Vec<String>) -> Vec<&str> {
+    names.iter().map(|name| name.as_str()).collect()
+}
+
+fn parse_is(nodes: &NodeSelector) -> String {
+    match *nodes {
+        AnyNode => ".Is()".to_string(),
+        Node(name) => format!(".Is(\"{}\")", escape_js_string(name)),
+        Nodes(ref names) => format!(".Is(\"{}\")", join_names(names)),
+        Float(..) | Decimal(..) => format!(".Is(\"{}\")", parse_number(nodes)),
+        NodeOwned(ref name) => parse_is(&Node(name.as_str())),
+        NodesOwned(ref names) => parse_is(&Nodes(borrow_names(names))),
+        Iri(..) | Literal(..) => parse_is(&Node(parse_term(nodes).as_str()))
+    }
+}
+
+// render a number node the same way every time, so it matches how it was stored
+fn parse_number(number: &NodeSelector) -> String {
+    match *number {
+        Float(value) => format!("{}", value),
+        Decimal(value, places) => format!("{:.*}", places, value),
+        _ => "".to_string()
+    }
+}
+
+// render an IRI or a typed literal the way Cayley stores them, `<iri>` and `"value"^^<type>`,
+// the result is a node name, escaped as any other one
+fn parse_term(term: &NodeSelector) -> String {
+    match *term {
+        Iri(iri) => format!("<{}>", unbracket(iri)),
+        Literal(value, datatype) => format!("\"{}\"^^<{}>", escape_literal(value), expand_datatype(unbracket(datatype))),
+        _ => "".to_string()
+    }
+}
+
+fn unbracket(iri: &str) -> &str {
+    iri.trim_left_matches('<').trim_right_matches('>')
+}
+
+// a literal value is put inside the quotes of a term, so quotes and backslashes in it
+// are escaped the N-Quads way, before the whole term is escaped as a JavaScript string
+fn escape_literal(value: &str) -> String {
+    value.replace("\\", "\\\\").replace("\"", "\\\"")
+}
+
+// Cayley doesn't know prefixes, so `xsd:` is expanded to the full XML Schema IRI
+fn expand_datatype(datatype: &str) -> String {
+    if datatype.starts_with(XSD_PREFIX) {
+        format!("{}{}", XSD_NAMESPACE, &datatype[XSD_PREFIX.len()..])
+    } else { datatype.to_string() }
+}
+
+const XSD_PREFIX: &'static str = "xsd:";
+const XSD_NAMESPACE: &'static str = "http://www.w3.org/2001/XMLSchema#";
+
 fn parse_final(_final: &Final) -> String {
     match _final {
         /* FIXME: Final:: shouldn't be required */
         &Final::Undefined => "".to_string(),
         &Final::All => ".All()".to_string(),
-        &Final::GetLimit(n) => format!(".GetLimit({})", n),
+        &Final::GetLimit(n) | &Final::FirstN(n) => format!(".GetLimit({})", n),
+        &Final::First => ".GetLimit(1)".to_string(),
         &Final::ToArray => ".ToArray()".to_string(),
         &Final::ToValue => ".ToValue()".to_string(),
         &Final::TagArray => ".TagArray()".to_string(),
-        &Final::TagValue => ".TagValue()".to_string()
+        &Final::TagValue => ".TagValue()".to_string(),
+        &Final::Count => ".Count()".to_string(),
+        &Final::ForEach(body) => format!(".ForEach(function(d){{ {} }})", body)
     }
 }
 
-fn parse_predicates_and_tags(predicates: &PredicateSelector, tags: &TagSelector) -> String {
+// excluded predicates are passed as a route of all nodes except them
+fn parse_excluded_predicates(root_name: &str, names: &[&str]) -> CompiledRoute {
+    CompiledRoute { prefix: "".to_string(),
+                    value: format!("{0}.V().Except({0}.V(\"{1}\"))", root_name, join_names(names)) }
+}
+
+// matching predicates are passed as a route of all nodes filtered with the pattern
+fn parse_matching_predicates(root_name: &str, pattern: &str) -> CompiledRoute {
+    CompiledRoute { prefix: "".to_string(),
+                    value: format!("{0}.V().Filter({1})", root_name, parse_comparison(&Comparison::Regex(pattern))) }
+}
+
+fn parse_predicates_and_tags(root_name: &str, predicates: &PredicateSelector, tags: &TagSelector) -> String {
+    match *predicates {
+        NotPredicates(ref names) =>
+            return parse_predicates_and_tags(root_name, &FromRoute(&parse_excluded_predicates(root_name, names)), tags),
+        MatchingPredicates(pattern) =>
+            return parse_predicates_and_tags(root_name, &FromRoute(&parse_matching_predicates(root_name, pattern)), tags),
+        _ => {}
+    };
     match (predicates, tags) {
 
         (&AnyPredicate, &AnyTag) => "".to_string(),
-        (&AnyPredicate, &Tag(tag)) => format!("null,\"{0}\"", tag),
-        (&AnyPredicate, &Tags(ref tags)) => format!("null,[\"{0}\"]", tags.connect("\",\"")),
+        (&AnyPredicate, &Tag(tag)) => format!("null,\"{0}\"", escape_js_string(tag)),
+        (&AnyPredicate, &Tags(ref tags)) => format!("null,[\"{0}\"]", join_names(tags)),
 
-        (&Predicate(predicate), &AnyTag) => format!("\"{0}\"", predicate),
+        (&Predicate(predicate), &AnyTag) => format!("\"{0}\"", escape_js_string(predicate)),
         (&Predicate(predicate), &Tag(tag)) =>
-            format!("\"{0}\",\"{1}\"", predicate, tag),
+            format!("\"{0}\",\"{1}\"", escape_js_string(predicate), escape_js_string(tag)),
         (&Predicate(predicate), &Tags(ref tags)) =>
-            format!("\"{0}\",[\"{1}\"]", predicate, tags.connect("\",\"")),
+            format!("\"{0}\",[\"{1}\"]", escape_js_string(predicate), join_names(tags)),
 
         (&Predicates(ref predicates), &AnyTag) =>
-            format!("[\"{0}\"]", predicates.connect("\",\"")),
+            format!("[\"{0}\"]", join_names(predicates)),
         (&Predicates(ref predicates), &Tag(tag)) =>
-            format!("[\"{0}\"],\"{1}\"", predicates.connect("\",\""), tag),
+            format!("[\"{0}\"],\"{1}\"", join_names(predicates), escape_js_string(tag)),
         (&Predicates(ref predicates), &Tags(ref tags)) =>
-            format!("[\"{0}\"],[\"{1}\"]", predicates.connect("\",\""), tags.connect("\",\"")),
+            format!("[\"{0}\"],[\"{1}\"]", join_names(predicates), join_names(tags)),
 
         (&FromRoute(route), &AnyTag) => route.value.clone(),
         (&FromRoute(route), &Tag(tag)) =>
-            format!("{0}, \"{1}\"", route.value, tag),
+            format!("{0}, \"{1}\"", route.value, escape_js_string(tag)),
         (&FromRoute(route), &Tags(ref tags)) =>
-            format!("{0}, [\"{1}\"]", route.value, tags.connect("\",\""))
+            format!("{0}, [\"{1}\"]", route.value, join_names(tags)),
+
+        // excluded and matching predicates are passed as a route above
+        (&NotPredicates(..), _) | (&MatchingPredicates(..), _) => unreachable!()
 
     }
 }
 
-fn parse_predicates_and_nodes(predicates: &PredicateSelector, nodes: &NodeSelector) -> String {
+fn parse_predicates_and_nodes(root_name: &str, predicates: &PredicateSelector, nodes: &NodeSelector) -> String {
+    match *predicates {
+        NotPredicates(ref names) =>
+            return parse_predicates_and_nodes(root_name, &FromRoute(&parse_excluded_predicates(root_name, names)), nodes),
+        MatchingPredicates(pattern) =>
+            return parse_predicates_and_nodes(root_name, &FromRoute(&parse_matching_predicates(root_name, pattern)), nodes),
+        _ => {}
+    };
+    match *nodes {
+        Float(..) | Decimal(..) => {
+            let number = parse_number(nodes);
+            return parse_predicates_and_nodes(root_name, predicates, &Node(number.as_str()))
+        },
+        NodeOwned(ref name) =>
+            return parse_predicates_and_nodes(root_name, predicates, &Node(name.as_str())),
+        NodesOwned(ref names) =>
+            return parse_predicates_and_nodes(root_name, predicates, &Nodes(borrow_names(names))),
+        Iri(..) | Literal(..) => {
+            let term = parse_term(nodes);
+            return parse_predicates_and_nodes(root_name, predicates, &Node(term.as_str()))
+        },
+        _ => {}
+    };
     match (predicates, nodes) {
 
         (&AnyPredicate, &AnyNode) => "".to_string(),
-        (&AnyPredicate, &Node(node)) => format!("null,\"{0}\"", node),
-        (&AnyPredicate, &Nodes(ref nodes)) => format!("null,[\"{0}\"]", nodes.connect("\",\"")),
+        (&AnyPredicate, &Node(node)) => format!("null,\"{0}\"", escape_js_string(node)),
+        (&AnyPredicate, &Nodes(ref nodes)) => format!("null,[\"{0}\"]", join_names(nodes)),
 
-        (&Predicate(predicate), &AnyNode) => format!("\"{0}\"", predicate),
+        (&Predicate(predicate), &AnyNode) => format!("\"{0}\"", escape_js_string(predicate)),
         (&Predicate(predicate), &Node(tag)) =>
-            format!("\"{0}\",\"{1}\"", predicate, tag),
+            format!("\"{0}\",\"{1}\"", escape_js_string(predicate), escape_js_string(tag)),
         (&Predicate(predicate), &Nodes(ref nodes)) =>
-            format!("\"{0}\",[\"{1}\"]", predicate, nodes.connect("\",\"")),
+            format!("\"{0}\",[\"{1}\"]", escape_js_string(predicate), join_names(nodes)),
 
         (&Predicates(ref predicates), &AnyNode) =>
-            format!("[\"{0}\"]", predicates.connect("\",\"")),
+            format!("[\"{0}\"]", join_names(predicates)),
         (&Predicates(ref predicates), &Node(node)) =>
-            format!("[\"{0}\"],\"{1}\"", predicates.connect("\",\""), node),
+            format!("[\"{0}\"],\"{1}\"", join_names(predicates), escape_js_string(node)),
         (&Predicates(ref predicates), &Nodes(ref nodes)) =>
-            format!("[\"{0}\"],[\"{1}\"]", predicates.connect("\",\""), nodes.connect("\",\"")),
+            format!("[\"{0}\"],[\"{1}\"]", join_names(predicates), join_names(nodes)),
 
         (&FromRoute(route), &AnyNode) => route.value.clone(),
         (&FromRoute(route), &Node(node)) =>
-            format!("{0},\"{1}\"", route.value, node),
+            format!("{0},\"{1}\"", route.value, escape_js_string(node)),
         (&FromRoute(route), &Nodes(ref nodes)) =>
-            format!("{0},[\"{1}\"]", route.value, nodes.connect("\",\""))
+            format!("{0},[\"{1}\"]", route.value, join_names(nodes)),
+
+        // excluded and matching predicates are passed as a route above
+        (&NotPredicates(..), _) | (&MatchingPredicates(..), _) => unreachable!(),
+        // number nodes are passed as a single node above
+        (_, &Float(..)) | (_, &Decimal(..)) => unreachable!(),
+        // owned nodes are passed as borrowed ones above
+        (_, &NodeOwned(..)) | (_, &NodesOwned(..)) => unreachable!(),
+        // IRIs and literals are passed as a single node above
+        (_, &Iri(..)) | (_, &Literal(..)) => unreachable!()
 
     }
 }
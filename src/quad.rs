@@ -0,0 +1,76 @@
+extern crate rustc_serialize;
+
+use self::rustc_serialize::{Encoder, Encodable};
+
+use error::Result as QuadResult;
+use error::Error::InvalidQuad;
+
+/// A single link in a graph, `subject -predicate-> object`, optionally
+/// put in a named subgraph with `label`. This is what Cayley stores and
+/// what is written to or deleted from it.
+///
+/// Cayley rejects quads with empty parts without telling which part was
+/// wrong, so constructors check every part and fail with `InvalidQuad`
+/// naming it, if it's empty or consists only of whitespace.
+///
+/// ```ignore
+/// let quad = Quad::new("/en/casablanca_1942", "name", "Casablanca").unwrap();
+/// let labeled = Quad::with_label("A", "follows", "B", "social").unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quad {
+    pub subject: String,
+    pub predicate: String,
+    pub object: String,
+    pub label: Option<String>
+}
+
+impl Quad {
+
+    /// Create a quad with no label, in the default subgraph
+    pub fn new(subject: &str, predicate: &str, object: &str) -> QuadResult<Quad> {
+        match (check("subject", subject), check("predicate", predicate), check("object", object)) {
+            (Ok(_), Ok(_), Ok(_)) => Ok(Quad { subject: subject.to_string(),
+                                               predicate: predicate.to_string(),
+                                               object: object.to_string(),
+                                               label: None }),
+            (Err(error), _, _) | (_, Err(error), _) | (_, _, Err(error)) => Err(error)
+        }
+    }
+
+    /// Create a quad in a subgraph named `label`
+    pub fn with_label(subject: &str, predicate: &str, object: &str, label: &str) -> QuadResult<Quad> {
+        match (Quad::new(subject, predicate, object), check("label", label)) {
+            (Ok(quad), Ok(_)) => Ok(Quad { label: Some(label.to_string()), ..quad }),
+            (Err(error), _) | (_, Err(error)) => Err(error)
+        }
+    }
+
+}
+
+// encoded the way Cayley expects quads to be written, with no `label` key when there's no label
+impl Encodable for Quad {
+
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+        let fields = if self.label.is_some() { 4 } else { 3 };
+        encoder.emit_struct("Quad", fields, |encoder| {
+            match encoder.emit_struct_field("subject", 0, |encoder| self.subject.encode(encoder))
+                  .and_then(|_| encoder.emit_struct_field("predicate", 1, |encoder| self.predicate.encode(encoder)))
+                  .and_then(|_| encoder.emit_struct_field("object", 2, |encoder| self.object.encode(encoder))) {
+                Err(error) => Err(error),
+                Ok(_) => match self.label {
+                    Some(ref label) => encoder.emit_struct_field("label", 3, |encoder| label.encode(encoder)),
+                    None => Ok(())
+                }
+            }
+        })
+    }
+
+}
+
+fn check(part: &str, value: &str) -> QuadResult<()> {
+    match value.trim().is_empty() {
+        true => Err(InvalidQuad(part.to_string())),
+        false => Ok(())
+    }
+}
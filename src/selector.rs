@@ -1,16 +1,43 @@
+use std::iter::FromIterator;
+
 use path;
 
 pub enum NodeSelector<'ns> {
     AnyNode,
     Node(&'ns str),
-    Nodes(Vec<&'ns str>)
+    Nodes(Vec<&'ns str>),
+    /// A node which is a number, rendered in the shortest form which reads back
+    /// as the same number, `Float(1.5)` is `"1.5"`
+    Float(f64),
+    /// A node which is a number, rendered with exactly given number of decimal
+    /// places, `Decimal(1.5, 2)` is `"1.50"`
+    Decimal(f64, usize),
+    /// Same as `Node`, but owns its id, i.e. when it came from another query
+    NodeOwned(String),
+    /// Same as `Nodes`, but owns the ids, i.e. when they came from another query
+    NodesOwned(Vec<String>),
+    /// A node which is an IRI, rendered in angle brackets, `Iri("http://example/Bob")`
+    /// is `"<http://example/Bob>"`, the brackets may be given already
+    Iri(&'ns str),
+    /// A node which is a typed literal, a value with a datatype IRI,
+    /// `Literal("42", "xsd:integer")` is `"\"42\"^^<http://www.w3.org/2001/XMLSchema#integer>"`,
+    /// the `xsd:` prefix is expanded, quotes and backslashes in the value are escaped
+    Literal(&'ns str, &'ns str)
 }
 
 pub enum PredicateSelector<'ps> {
     AnyPredicate,
     Predicate(&'ps str),
     Predicates(Vec<&'ps str>),
-    Route(&'ps path::CompiledRoute)
+    Route(&'ps path::CompiledRoute),
+    /// Any predicate except the given ones, rendered as a route of all the
+    /// nodes except these: `g.V().Except(g.V("a","b"))`, so Cayley has to
+    /// iterate over all the nodes to find the predicates to follow
+    Not(Vec<&'ps str>),
+    /// Any predicate matching the regular expression, rendered as a route of all
+    /// the nodes filtered with it: `g.V().Filter(regex("^/film/"))`, so, same as
+    /// with `Not`, Cayley has to iterate over all the nodes to find the predicates
+    Regex(&'ps str)
 }
 
 pub enum TagSelector<'ts> {
@@ -18,3 +45,45 @@ pub enum TagSelector<'ts> {
     Tag(&'ts str),
     Tags(Vec<&'ts str>)
 }
+
+/// `Nodes` from a slice of names, i.e. `NodeSelector::from(&["foo", "bar"][..])`
+impl<'ns> From<&'ns [&'ns str]> for NodeSelector<'ns> {
+    fn from(names: &'ns [&'ns str]) -> NodeSelector<'ns> {
+        NodeSelector::Nodes(names.to_vec())
+    }
+}
+
+/// `Nodes` from any names, i.e. `names.iter().cloned().collect::<NodeSelector>()`
+impl<'ns> FromIterator<&'ns str> for NodeSelector<'ns> {
+    fn from_iter<I: IntoIterator<Item = &'ns str>>(names: I) -> NodeSelector<'ns> {
+        NodeSelector::Nodes(names.into_iter().collect())
+    }
+}
+
+/// `Predicates` from a slice of names, i.e. `PredicateSelector::from(&["foo", "bar"][..])`
+impl<'ps> From<&'ps [&'ps str]> for PredicateSelector<'ps> {
+    fn from(names: &'ps [&'ps str]) -> PredicateSelector<'ps> {
+        PredicateSelector::Predicates(names.to_vec())
+    }
+}
+
+/// `Predicates` from any names, i.e. `names.iter().cloned().collect::<PredicateSelector>()`
+impl<'ps> FromIterator<&'ps str> for PredicateSelector<'ps> {
+    fn from_iter<I: IntoIterator<Item = &'ps str>>(names: I) -> PredicateSelector<'ps> {
+        PredicateSelector::Predicates(names.into_iter().collect())
+    }
+}
+
+/// `Tags` from a slice of names, i.e. `TagSelector::from(&["foo", "bar"][..])`
+impl<'ts> From<&'ts [&'ts str]> for TagSelector<'ts> {
+    fn from(names: &'ts [&'ts str]) -> TagSelector<'ts> {
+        TagSelector::Tags(names.to_vec())
+    }
+}
+
+/// `Tags` from any names, i.e. `names.iter().cloned().collect::<TagSelector>()`
+impl<'ts> FromIterator<&'ts str> for TagSelector<'ts> {
+    fn from_iter<I: IntoIterator<Item = &'ts str>>(names: I) -> TagSelector<'ts> {
+        TagSelector::Tags(names.into_iter().collect())
+    }
+}
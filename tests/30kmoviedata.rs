@@ -4,7 +4,7 @@
 #[phase(plugin, link)]
 extern crate cayley;
 
-use cayley::{Graph, V1};
+use cayley::{Graph, Quad, V1};
 use cayley::Nodes as GraphNodes;
 
 use cayley::selectors::*;
@@ -127,37 +127,53 @@ fn main() {
                 }
             }
 
+            match graph.write(&[ Quad::new("/zh/new_movie", "name", "New Movie").unwrap() ]) {
+
+                Err(error) => panic!(error.to_string()),
+                Ok(written) => assert_eq!(written, 1)
+
+            }
+
+            match graph.find(vertex!(Node("New Movie") -> InP(Predicate("name")) => All)) {
+
+                Err(error) => panic!(error.to_string()),
+                Ok(GraphNodes(nodes)) => {
+                    assert_eq!(nodes.len(), 1);
+                    assert_eq!(nodes[0].id().unwrap(), "/zh/new_movie");
+                }
+
+            }
+
+            match graph.delete(&[ Quad::new("/zh/new_movie", "name", "New Movie").unwrap() ]) {
+
+                Err(error) => panic!(error.to_string()),
+                Ok(deleted) => assert_eq!(deleted, 1)
+
+            }
+
+            match graph.find_emitted(vertex!(Node("Casablanca") => ForEach("g.Emit(d);"))) {
+
+                Err(error) => panic!(error.to_string()),
+                Ok(emitted) => assert!(emitted.len() > 0)
+
+            }
+
+            match graph.shape(vertex!(Node("Casablanca") => All)) {
+
+                Err(error) => panic!(error.to_string()),
+                Ok(shape) => {
+                    assert!(shape.links.is_empty());
+                    assert!(shape.nodes.len() > 0);
+                }
+
+            }
+
         }
 
         // TODO: ensure ToValue(), ToArray(), TagValue(), TagArray() do fail
 
     }
 
-    // graph.write([ { subject: "Subject Node",
-    //                 predicate: "Predicate Node",
-    //                 object: "Object Node" }]);
-    //
-    // graph.delete([ { subject: "Subject Node",
-    //                 predicate: "Predicate Node",
-    //                 object: "Object Node" }]);
-
-    // it('test Emit', function(done) {
-    //     this.timeout(10000);
-    //     g.V("Casablanca").ForEach(function(d) { g.Emit(d); }, function(err, result) {
-    //         assert(result.length);
-    //         done(err);
-    //     });
-    // });
-    //
-    // it('test type shape', function(done) {
-    //     this.timeout(10000);
-    //     var graph = g.type('shape');
-    //     graph.V("Casablanca").All(function(err, result) {
-    //         assert(!result.links && result.nodes);
-    //         done(err);
-    //     });
-    // });
-    //
     // it('test write', function(done)  {
     //     client.write([{
     //         subject: "/zh/new_movie",
@@ -186,3 +202,23 @@ fn main() {
     // });
 
 }
+
+#[test]
+fn test_node_ids() {
+
+    let graph = Graph::new("localhost", 64210, V1).unwrap();
+
+    match graph.find(vertex!(Node("Humphrey Bogart") => All)) {
+        Err(error) => panic!(error.to_string()),
+        Ok(nodes) => {
+            assert_eq!(nodes.ids(), vec!["Humphrey Bogart"]);
+            assert_eq!(nodes.iter().next().unwrap().id(), Some("Humphrey Bogart"));
+        }
+    }
+
+    match graph.find(vertex!(Node("Casablanca") -> InP(Predicate("name")) => All)) {
+        Err(error) => panic!(error.to_string()),
+        Ok(nodes) => assert_eq!(nodes.ids(), vec!["/en/casablanca_1942"])
+    }
+
+}
@@ -0,0 +1,207 @@
+extern crate cayley;
+extern crate rustc_serialize;
+
+use rustc_serialize::json::decode as json_decode;
+
+use std::collections::HashMap;
+
+use cayley::graph::{Nodes, ResultRow};
+use cayley::graph::Value;
+use cayley::graph::{decode_items, decode_multitags, decode_ordered};
+use cayley::graph::{OrderedNodes, OrderedNode};
+
+fn decode(source: &str) -> Nodes {
+    match json_decode(source) {
+        Ok(nodes) => nodes,
+        Err(error) => panic!(error.to_string())
+    }
+}
+
+// == Node ==
+
+#[test]
+fn test_node_primary() {
+
+    let Nodes(nodes) = decode("{\"result\":[{\"id\":\"foo\"},\
+                                            {\"value\":\"bar\"},\
+                                            {\"id\":\"buz\",\"value\":\"qux\"},\
+                                            {\"target\":\"B\"},\
+                                            {\"source\":\"A\",\"target\":\"B\"}]}");
+
+    assert_eq!(nodes.len(), 5);
+    assert_eq!(nodes[0].primary(), Some("foo"));
+    assert_eq!(nodes[1].primary(), Some("bar"));
+    assert_eq!(nodes[2].primary(), Some("buz"));
+    assert_eq!(nodes[3].primary(), Some("B"));
+    assert_eq!(nodes[4].primary(), None);
+
+    assert_eq!(nodes[0]["id".to_string()].as_slice(), "foo");
+
+}
+
+// == Nodes ==
+
+#[test]
+fn test_nodes_accessors() {
+
+    let nodes = decode("{\"result\":[{\"id\":\"foo\",\"name\":\"Foo\"},{\"id\":\"bar\"}]}");
+
+    assert_eq!(nodes.len(), 2);
+    assert!(!nodes.is_empty());
+    assert_eq!(nodes.iter().map(|node| node.primary().unwrap()).collect::<Vec<_>>(), vec!["foo", "bar"]);
+    assert_eq!(nodes.to_string(), "{id: foo, name: Foo}\n{id: bar}");
+
+    assert!(decode("{\"result\":null}").is_empty());
+
+    let nodes = decode("{\"result\":[{\"id\":\"foo\"},{\"value\":\"bar\"},{\"id\":\"buz\"}]}");
+    assert_eq!(nodes.ids(), vec!["foo", "buz"]);
+    assert_eq!(nodes.iter().next().unwrap().id(), Some("foo"));
+    assert_eq!(nodes.iter().nth(1).unwrap().id(), None);
+
+}
+
+// == ResultRow ==
+
+#[test]
+fn test_result_row_accessors() {
+
+    let Nodes(mut nodes) = decode("{\"result\":[{\"id\":\"B\",\"source\":\"A\",\"label\":\"graph-1\"},\
+                                                {\"source\":\"A\"}]}");
+    let row = ResultRow::from(nodes.remove(0));
+    assert_eq!(row.id(), Some("B"));
+    assert_eq!(row.tag("source"), Some("A"));
+    assert_eq!(row.tag("label"), Some("graph-1"));
+    assert_eq!(row.tag("id"), None);
+    assert_eq!(row.tag("target"), None);
+    assert_eq!(row.tags(), vec![("label", "graph-1"), ("source", "A")]);
+    assert_eq!(row["label".to_string()].as_slice(), "graph-1");
+
+    let row = ResultRow::from(nodes.remove(0));
+    assert_eq!(row.id(), None);
+    assert_eq!(row.tags(), vec![("source", "A")]);
+
+}
+
+#[test]
+fn test_nodes_into_iter() {
+
+    let nodes = decode("{\"result\":[{\"id\":\"foo\"},{\"id\":\"bar\"}]}");
+
+    let mut ids = Vec::new();
+    for node in &nodes { ids.push(node["id"].clone()); }
+    assert_eq!(ids, vec!["foo".to_string(), "bar".to_string()]);
+
+    let mut ids = Vec::new();
+    for node in nodes { ids.push(node.primary().unwrap().to_string()); }
+    assert_eq!(ids, vec!["foo".to_string(), "bar".to_string()]);
+
+}
+
+// == JSON Lines ==
+
+#[test]
+fn test_nodes_jsonl() {
+
+    let nodes = decode("{\"result\":[{\"id\":\"foo\"},{\"id\":\"bar\"}]}");
+
+    let mut output: Vec<u8> = Vec::new();
+    assert_eq!(nodes.write_jsonl(&mut output).unwrap(), 2);
+    assert_eq!(String::from_utf8(output).unwrap().as_slice(),
+               "{\"id\":\"foo\"}\n{\"id\":\"bar\"}\n");
+
+    let mut output: Vec<u8> = Vec::new();
+    assert_eq!(decode("{\"result\":null}").write_jsonl(&mut output).unwrap(), 0);
+    assert!(output.is_empty());
+
+}
+
+// == Nested values ==
+
+#[test]
+fn test_nested_values() {
+
+    fn node(id: &str) -> Value {
+        let mut map = HashMap::new();
+        map.insert("id".to_string(), id.to_string());
+        Value::Node(map)
+    }
+
+    let values = Value::decode_result("{\"result\":[{\"id\":\"foo\"},\
+                                                     [{\"id\":\"bar\"},{\"id\":\"buz\"}],\
+                                                     {\"group\":[{\"id\":\"qux\"}],\"size\":1},\
+                                                     null]}").unwrap();
+
+    assert_eq!(values.len(), 4);
+    assert_eq!(values[0], node("foo"));
+    assert_eq!(values[1], Value::List(vec![node("bar"), node("buz")]));
+    let mut group = HashMap::new();
+    group.insert("group".to_string(), Value::List(vec![node("qux")]));
+    group.insert("size".to_string(), Value::Text("1".to_string()));
+    assert_eq!(values[2], Value::Group(group));
+    assert_eq!(values[3], Value::Null);
+
+    assert_eq!(Value::decode_result("{\"result\":null}").unwrap(), vec![]);
+    assert!(Value::decode_result("{\"error\":\"no such path\"}").is_err());
+
+}
+
+// == Tags into structs ==
+
+#[derive(RustcDecodable, Debug, PartialEq)]
+struct Follows {
+    source: String,
+    target: String
+}
+
+#[test]
+fn test_decode_tagged_items() {
+
+    let follows: Vec<Follows> = decode_items("{\"result\":[{\"id\":\"A\",\"source\":\"A\",\"target\":\"B\"},\
+                                                           {\"id\":\"C\",\"source\":\"C\",\"target\":\"D\"}]}").unwrap();
+    assert_eq!(follows, vec![Follows { source: "A".to_string(), target: "B".to_string() },
+                             Follows { source: "C".to_string(), target: "D".to_string() }]);
+
+    // a tag the struct requires is missing
+    assert!(decode_items::<Follows>("{\"result\":[{\"id\":\"A\",\"source\":\"A\"}]}").is_err());
+
+}
+
+// == Multi-valued tags ==
+
+#[test]
+fn test_decode_multitags() {
+
+    let nodes = decode_multitags("{\"result\":[{\"id\":\"C\",\"follower\":\"A\"},\
+                                                {\"id\":\"C\",\"follower\":\"B\"},\
+                                                {\"id\":\"C\",\"follower\":\"A\"},\
+                                                {\"id\":\"D\",\"follower\":[\"B\",\"E\"]}]}").unwrap();
+
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(nodes[0]["id"], vec!["C".to_string()]);
+    assert_eq!(nodes[0]["follower"], vec!["A".to_string(), "B".to_string()]);
+    assert_eq!(nodes[1]["id"], vec!["D".to_string()]);
+    assert_eq!(nodes[1]["follower"], vec!["B".to_string(), "E".to_string()]);
+
+}
+
+// == Ordered nodes ==
+
+#[test]
+fn test_decode_ordered() {
+
+    fn pairs(pairs: &[(&str, &str)]) -> OrderedNode {
+        OrderedNode(pairs.iter().map(|&(key, value)| (key.to_string(), value.to_string())).collect())
+    }
+
+    let OrderedNodes(nodes) = decode_ordered("{\"result\":[{\"target\":\"B\",\"source\":\"A\",\"id\":\"B\"},\
+                                                           {\"year\":1942,\"nested\":{\"skip\":\"me\"},\"id\":\"C\"}]}").unwrap();
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(nodes[0], pairs(&[("target", "B"), ("source", "A"), ("id", "B")]));
+    assert_eq!(nodes[1], pairs(&[("year", "1942"), ("id", "C")]));
+    assert_eq!(nodes[1].get("id"), Some("C"));
+
+    assert_eq!(decode_ordered("{\"result\":null}").unwrap(), OrderedNodes(vec![]));
+    assert!(decode_ordered("{\"error\":\"no such path\"}").is_err());
+    assert!(decode_ordered("{\"result\":[{").is_err());
+
+}
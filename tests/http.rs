@@ -0,0 +1,1372 @@
+#![feature(phase, macro_rules)]
+
+#[phase(plugin, link)]
+extern crate cayley;
+extern crate hyper;
+extern crate rustc_serialize;
+extern crate log;
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use hyper::method::Method;
+use hyper::status::StatusCode;
+
+use cayley::{Graph, Quad, V1};
+use cayley::graph::{MAX_GET_URL_LENGTH, DEFAULT_CONTENT_TYPE, DEFAULT_ROOT_NAME};
+use cayley::Error::{ResponseTooLarge, DecodingFailed, RequestFailed, CircuitOpen, ExpectationNotSupported,
+                    TooManyResults, ResultRejected, ExpectationMismatch, PartialResult, PrefixTooLarge, QuadNotFound,
+                    RequestTimedOut, ServerError, HttpStatus, InvalidTraceId, RootNameMismatch};
+use cayley::graph::CircuitState::{Closed, Open};
+use cayley::graph::BatchMode::{CollectAll, AbortOnError};
+use cayley::graph::NodeValue::{Num, Str};
+use cayley::graph::{Value, OrderedNodes};
+use cayley::path::Expectation::{NodeSequence, SingleNode};
+use cayley::path::{Vertex, Morphism};
+use cayley::path::Final::{All, GetLimit, ToArray, ToValue, TagArray, TagValue, Count, ForEach, First};
+use cayley::path::Traversal::{Out, OutP, InP, As, Follow};
+use cayley::selectors::{AnyNode, Node, Predicate, Predicates, Tag};
+
+// A one-shot HTTP server: accepts a single connection, sends the raw request
+// it received into the returned channel and answers with a given JSON body
+fn serve(response_body: &str) -> (u16, Receiver<String>) {
+    serve_all(vec![response_body])
+}
+
+// Same as `serve`, but accepts a connection per given body, in order;
+// on `DROP`, the connection is closed without any response, a body starting
+// with `HTTP/` is sent as a complete response
+const DROP: &'static str = "";
+
+fn serve_all(response_bodies: Vec<&str>) -> (u16, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let responses: Vec<String> = response_bodies.iter().map(|body| {
+        if body.is_empty() { return String::new() }
+        // a complete response, i.e. with some other status
+        if body.starts_with("HTTP/") { return body.to_string() }
+        format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+    }).collect();
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for response in responses.iter() {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            loop {
+                let read = stream.read(&mut buffer).unwrap();
+                request.push_all(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request).into_owned();
+                match text.find("\r\n\r\n") {
+                    Some(head_end) => {
+                        let length = text[..head_end].lines()
+                                         .filter(|line| line.to_lowercase().starts_with("content-length:"))
+                                         .map(|line| line[15..].trim().parse::<usize>().unwrap())
+                                         .next().unwrap_or(0);
+                        if request.len() >= head_end + 4 + length { break; }
+                    },
+                    None => if read == 0 { break; }
+                }
+            }
+            if response.is_empty() { continue; }
+            stream.write_all(response.as_bytes()).unwrap();
+            // the receiving side may have been dropped by a test not interested in requests
+            let _ = sender.send(String::from_utf8_lossy(&request).into_owned());
+        }
+    });
+    (port, receiver)
+}
+
+// A server which accepts a connection, but never answers it (for `hold` time)
+fn hang(hold: Duration) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (_stream, _) = listener.accept().unwrap();
+        thread::sleep(hold);
+    });
+    port
+}
+
+// The response to a capability probe from Cayley supporting the probed Final
+const PROBED: &'static str = "{\"result\":null}";
+
+fn graph_at(port: u16) -> Graph {
+    Graph::default_to("127.0.0.1", port as i32).unwrap()
+}
+
+// A logger keeping every line the driver writes, tests run in parallel,
+// so they look only for the lines they are sure to be the authors of
+struct Recorder;
+
+static RECORDED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static RECORDER: Recorder = Recorder;
+
+impl log::Log for Recorder {
+    fn enabled(&self, _: &log::Metadata) -> bool { true }
+    fn log(&self, record: &log::Record) {
+        RECORDED.lock().unwrap().push(format!("{}", record.args()));
+    }
+    fn flush(&self) {}
+}
+
+fn record_logs() {
+    // fails if already set by another test, the recorder stays then
+    let _ = log::set_logger(&RECORDER);
+    log::set_max_level(log::LevelFilter::Debug);
+}
+
+fn recorded_with(mark: &str) -> Vec<String> {
+    RECORDED.lock().unwrap().iter().filter(|line| line.contains(mark)).cloned().collect()
+}
+
+// == Connection ==
+
+#[test]
+fn test_new_connected() {
+
+    let (port, request) = serve("{\"result\":[]}");
+    assert!(Graph::new_connected("127.0.0.1", port as i32, V1).is_ok());
+    assert!(request.recv().unwrap().ends_with("g.V().Limit(0).All()"));
+
+    // nothing listens on a port of a dropped listener
+    let dead_port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    match Graph::new_connected("127.0.0.1", dead_port as i32, V1) {
+        Err(RequestFailed(..)) => {},
+        _ => panic!("connection to a dead address should fail")
+    }
+
+}
+
+// == Response size ==
+
+#[test]
+fn test_max_response_bytes() {
+
+    let (port, _) = serve("{\"result\":[{\"id\":\"foo\"},{\"id\":\"bar\"}]}");
+    match graph_at(port).with_max_response_bytes(16)
+                        .exec("g.V().All()".to_string(), NodeSequence) {
+        Err(ResponseTooLarge(limit)) => assert_eq!(limit, 16),
+        Err(error) => panic!(error.to_string()),
+        Ok(_) => panic!("response should exceed the limit")
+    }
+
+    let (port, _) = serve("{\"result\":[{\"id\":\"foo\"}]}");
+    match graph_at(port).with_max_response_bytes(1024)
+                        .exec("g.V().All()".to_string(), NodeSequence) {
+        Err(error) => panic!(error.to_string()),
+        Ok(cayley::Nodes(nodes)) => assert_eq!(nodes.len(), 1)
+    }
+
+}
+
+// == Headers ==
+
+#[test]
+fn test_user_agent() {
+
+    let (port, request) = serve("{\"result\":[]}");
+    graph_at(port).exec("g.V().All()".to_string(), NodeSequence).unwrap();
+    let request = request.recv().unwrap();
+    assert!(request.contains(&format!("User-Agent: cayley-rust/{}\r\n", env!("CARGO_PKG_VERSION"))));
+
+    let (port, request) = serve("{\"result\":[]}");
+    graph_at(port).with_user_agent("my-app/1.0")
+                  .exec("g.V().All()".to_string(), NodeSequence).unwrap();
+    let request = request.recv().unwrap();
+    assert!(request.contains(&format!("User-Agent: cayley-rust/{} my-app/1.0\r\n", env!("CARGO_PKG_VERSION"))));
+
+}
+
+#[test]
+fn test_trace_id() {
+
+    let (port, request) = serve("{\"result\":[]}");
+    graph_at(port).find_traced(vertex![ AnyNode => All ], "req-42").unwrap();
+    assert!(request.recv().unwrap().contains("X-Request-ID: req-42\r\n"));
+
+    let (port, request) = serve("{\"result\":[]}");
+    graph_at(port).find(vertex![ AnyNode => All ]).unwrap();
+    assert!(!request.recv().unwrap().contains("X-Request-ID"));
+
+    // the id would end the header and add one of its own
+    match graph_at(1).find_traced(vertex![ AnyNode => All ], "req-42\r\nX-Admin: yes") {
+        Err(InvalidTraceId(trace_id)) => assert_eq!(trace_id, "req-42\r\nX-Admin: yes"),
+        other => panic!("expected InvalidTraceId, got {:?}", other)
+    }
+    match graph_at(1).find_traced(vertex![ AnyNode => All ], "req-\u{7f}") {
+        Err(InvalidTraceId(_)) => {},
+        other => panic!("expected InvalidTraceId, got {:?}", other)
+    }
+
+}
+
+#[test]
+fn test_trace_id_in_logs() {
+
+    record_logs();
+    let (port, _) = serve("{\"result\":[]}");
+    graph_at(port).find_traced(vertex![ AnyNode => All ], "trace-in-logs-1").unwrap();
+    let lines = recorded_with("trace-in-logs-1");
+    assert!(lines.iter().any(|line| line.starts_with("[trace-in-logs-1] Executing query: ")));
+    assert!(lines.iter().any(|line| line.starts_with("[trace-in-logs-1] Request to ") && line.ends_with(" succeeded")));
+    assert!(lines.iter().all(|line| line.starts_with("[trace-in-logs-1] ")));
+
+}
+
+#[test]
+fn test_content_type() {
+
+    let (port, request) = serve("{\"result\":[]}");
+    graph_at(port).find(vertex![ AnyNode => All ]).unwrap();
+    assert!(request.recv().unwrap().contains(&format!("Content-Type: {}\r\n", DEFAULT_CONTENT_TYPE)));
+
+    let (port, request) = serve("{\"result\":[]}");
+    graph_at(port).with_content_type("application/javascript").find(vertex![ AnyNode => All ]).unwrap();
+    assert!(request.recv().unwrap().contains("Content-Type: application/javascript\r\n"));
+
+}
+
+// == Pagination ==
+
+#[test]
+fn test_following_cursor() {
+
+    let (port, requests) = serve_all(vec!["{\"result\":[{\"id\":\"foo\"}],\"cursor\":\"page-2\"}",
+                                          "{\"result\":[{\"id\":\"bar\"}]}"]);
+    match graph_at(port).exec("g.V().All()".to_string(), NodeSequence) {
+        Err(error) => panic!(error.to_string()),
+        Ok(cayley::Nodes(nodes)) => {
+            assert_eq!(nodes.len(), 2);
+            assert_eq!(nodes[0].primary(), Some("foo"));
+            assert_eq!(nodes[1].primary(), Some("bar"));
+        }
+    }
+    assert!(requests.recv().unwrap().starts_with("POST /api/v1/query/gremlin HTTP/1.1"));
+    assert!(requests.recv().unwrap().starts_with("POST /api/v1/query/gremlin?cursor=page-2 HTTP/1.1"));
+
+    // a server sending the same cursor again would be asked for the pages forever
+    let (port, _) = serve_all(vec!["{\"result\":[{\"id\":\"foo\"}],\"cursor\":\"page-2\"}",
+                                   "{\"result\":[{\"id\":\"bar\"}],\"cursor\":\"page-2\"}"]);
+    match graph_at(port).exec("g.V().All()".to_string(), NodeSequence) {
+        Err(cayley::Error::CursorRepeated(cursor)) => assert_eq!(cursor.as_slice(), "page-2"),
+        _ => panic!("a repeated cursor should not be followed")
+    }
+
+}
+
+#[test]
+fn test_find_ordered_pages() {
+
+    let (port, requests) = serve_all(vec!["{\"result\":[{\"target\":\"B\",\"id\":\"B\"}],\"cursor\":\"page-2\"}",
+                                          "{\"result\":[{\"target\":\"D\",\"id\":\"D\"}]}"]);
+    let OrderedNodes(nodes) = graph_at(port).find_ordered(vertex![ Node("C") -> Out(Predicate("follows"), Tag("target"))
+                                                                  => All ]).unwrap();
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(nodes[0].0[0], ("target".to_string(), "B".to_string()));
+    assert_eq!(nodes[1].get("id"), Some("D"));
+    requests.recv().unwrap();
+    assert!(requests.recv().unwrap().starts_with("POST /api/v1/query/gremlin?cursor=page-2 HTTP/1.1"));
+
+}
+
+// == Degree ==
+
+#[test]
+fn test_find_by_degree() {
+
+    let edges = "{\"result\":[{\"__degree_source\":\"A\",\"id\":\"B\"},\
+                              {\"__degree_source\":\"A\",\"id\":\"C\"},\
+                              {\"__degree_source\":\"B\",\"id\":\"C\"},\
+                              {\"__degree_source\":\"D\",\"id\":\"A\"},\
+                              {\"__degree_source\":\"D\",\"id\":\"B\"},\
+                              {\"__degree_source\":\"D\",\"id\":\"C\"}]}";
+
+    // the edges are neither transformed nor validated, they are not the result
+    let (port, requests) = serve(edges);
+    let graph = graph_at(port).with_value_transform("__degree_source", |source| source.to_lowercase())
+                              .with_result_validator(|nodes| if nodes.len() > 3 { Err("too many".to_string()) } else { Ok(()) });
+    match graph.find_by_degree(vertex![ AnyNode ], "follows", 2) {
+        Err(error) => panic!(error.to_string()),
+        Ok(nodes) => assert_eq!(nodes.ids(), vec!["A", "D"])
+    }
+    assert!(requests.recv().unwrap().ends_with("g.V().As(\"__degree_source\").Out(\"follows\").All()"));
+
+    // a tag set in the route is kept as it is
+    let (port, requests) = serve(edges);
+    graph_at(port).find_by_degree(vertex![ AnyNode -> As(Tag("source")) ], "follows", 2).unwrap();
+    assert!(requests.recv().unwrap().ends_with("g.V().As(\"source\").As(\"__degree_source\").Out(\"follows\").All()"));
+
+    // the nodes with no edges are never found, so `0` is the same as `1`
+    let (port, _) = serve_all(vec![edges, edges]);
+    let graph = graph_at(port);
+    assert_eq!(graph.find_by_degree(vertex![ AnyNode ], "follows", 0).unwrap().ids(), vec!["A", "B", "D"]);
+    assert_eq!(graph.find_by_degree(vertex![ AnyNode ], "follows", 1).unwrap().ids(), vec!["A", "B", "D"]);
+
+}
+
+// == Circuit breaker ==
+
+#[test]
+fn test_circuit_breaker() {
+
+    let (port, _) = serve_all(vec![DROP, DROP, DROP, "{\"result\":[]}"]);
+    let graph = graph_at(port).with_circuit_breaker(2, Duration::from_millis(100));
+    assert_eq!(graph.circuit_state(), Some(Closed(0)));
+
+    match graph.exec("g.V().All()".to_string(), NodeSequence) {
+        Err(RequestFailed(..)) => assert_eq!(graph.circuit_state(), Some(Closed(1))),
+        _ => panic!("request should fail")
+    }
+    match graph.exec("g.V().All()".to_string(), NodeSequence) {
+        Err(RequestFailed(..)) => match graph.circuit_state() {
+            Some(Open(_)) => {}, state => panic!("circuit should be open, but it is {:?}", state)
+        },
+        _ => panic!("request should fail")
+    }
+    match graph.exec("g.V().All()".to_string(), NodeSequence) {
+        Err(CircuitOpen) => {}, _ => panic!("request should not be sent")
+    }
+
+    // the probe fails, so the circuit is open again
+    thread::sleep(Duration::from_millis(150));
+    match graph.exec("g.V().All()".to_string(), NodeSequence) {
+        Err(RequestFailed(..)) => match graph.circuit_state() {
+            Some(Open(_)) => {}, state => panic!("circuit should be open, but it is {:?}", state)
+        },
+        _ => panic!("probe request should fail")
+    }
+
+    // the probe succeeds, so the circuit is closed
+    thread::sleep(Duration::from_millis(150));
+    assert!(graph.exec("g.V().All()".to_string(), NodeSequence).is_ok());
+    assert_eq!(graph.circuit_state(), Some(Closed(0)));
+
+    // `5xx` responses count as failures, other statuses don't
+    let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    let not_found = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    let (port, _) = serve_all(vec![not_found, unavailable, unavailable]);
+    let graph = graph_at(port).with_circuit_breaker(2, Duration::from_millis(100));
+    match graph.exec("g.V().All()".to_string(), NodeSequence) {
+        Err(HttpStatus(..)) => assert_eq!(graph.circuit_state(), Some(Closed(0))),
+        _ => panic!("request should fail")
+    }
+    match graph.exec("g.V().All()".to_string(), NodeSequence) {
+        Err(HttpStatus(..)) => assert_eq!(graph.circuit_state(), Some(Closed(1))),
+        _ => panic!("request should fail")
+    }
+    match graph.exec("g.V().All()".to_string(), NodeSequence) {
+        Err(HttpStatus(..)) => match graph.circuit_state() {
+            Some(Open(_)) => {}, state => panic!("circuit should be open, but it is {:?}", state)
+        },
+        _ => panic!("request should fail")
+    }
+
+}
+
+// == Relationships ==
+
+#[test]
+fn test_relationships() {
+
+    let (port, requests) = serve("{\"result\":[{\"id\":\"B\",\"predicate\":\"follows\"},\
+                                              {\"id\":\"D\",\"predicate\":\"follows\"},\
+                                              {\"id\":\"cool_person\",\"predicate\":\"status\"}]}");
+    match graph_at(port).relationships("C") {
+        Err(error) => panic!(error.to_string()),
+        Ok(pairs) => assert_eq!(pairs, vec![("follows".to_string(), "B".to_string()),
+                                            ("follows".to_string(), "D".to_string()),
+                                            ("status".to_string(), "cool_person".to_string())])
+    }
+    assert!(requests.recv().unwrap().ends_with("g.V(\"C\").Out(null,\"predicate\").All()"));
+
+    // the edges are not a query result, so the validators and the transforms don't see them
+    let (port, _) = serve("{\"result\":[{\"id\":\"B\",\"predicate\":\"follows\"}]}");
+    let graph = graph_at(port).with_value_transform("predicate", |predicate| predicate.to_uppercase())
+                              .with_result_validator(|nodes| if nodes.len() < 2 { Err("too few".to_string()) } else { Ok(()) });
+    assert_eq!(graph.relationships("C").unwrap(), vec![("follows".to_string(), "B".to_string())]);
+
+}
+
+// == Capabilities ==
+
+#[test]
+fn test_capability_probe() {
+
+    // without probing, unsupported Finals are rejected without any request
+    match Graph::default().unwrap().exec("g.V().ToValue()".to_string(), SingleNode) {
+        Err(ExpectationNotSupported(SingleNode)) => {},
+        _ => panic!("ToValue() should be rejected")
+    }
+
+    // the server responds to the probe only, so the second query is answered from cache
+    let (port, requests) = serve("{\"error\":\"ToValue is not supported\"}");
+    let graph = graph_at(port).with_capability_probe();
+    for _ in 0..2 {
+        match graph.exec("g.V(\"foo\").ToValue()".to_string(), SingleNode) {
+            Err(ExpectationNotSupported(SingleNode)) => {},
+            _ => panic!("ToValue() should be rejected")
+        }
+    }
+    assert!(requests.recv().unwrap().ends_with("\r\n\r\ng.V().ToValue()"));
+
+    // nothing found for the probe is not an error, so the query itself is sent then,
+    // and the probe starts with the renamed root the same way the queries do
+    let (port, requests) = serve_all(vec!["{\"result\":null}", "{\"result\":null}"]);
+    let graph = graph_at(port).with_root_name("graph").with_capability_probe();
+    match graph.exec("graph.V(\"foo\").ToValue()".to_string(), SingleNode) {
+        Err(ExpectationNotSupported(_)) => panic!("ToValue() should be sent"),
+        _ => {}
+    }
+    assert!(requests.recv().unwrap().ends_with("\r\n\r\ngraph.V().ToValue()"));
+    assert!(requests.recv().unwrap().ends_with("\r\n\r\ngraph.V(\"foo\").ToValue()"));
+
+}
+
+// == Stats ==
+
+#[test]
+fn test_query_stats() {
+
+    let first = "{\"result\":[{\"id\":\"foo\"}],\"cursor\":\"page-2\"}";
+    let second = "{\"result\":[{\"id\":\"bar\"}]}";
+    let (port, _) = serve_all(vec![first, second]);
+    match graph_at(port).find_with_stats(vertex![ AnyNode => All ]) {
+        Err(error) => panic!(error.to_string()),
+        Ok((cayley::Nodes(nodes), stats)) => {
+            assert_eq!(nodes.len(), 2);
+            assert_eq!(stats.requests, 2);
+            assert_eq!(stats.request_bytes, 2 * "g.V().All()".len());
+            assert_eq!(stats.response_bytes, first.len() + second.len());
+        }
+    }
+
+}
+
+// == Nodes lookup ==
+
+#[test]
+fn test_find_nodes() {
+
+    let (port, requests) = serve("{\"result\":[{\"id\":\"A\"},{\"id\":\"C\"}]}");
+    match graph_at(port).find_nodes(&["A", "B", "C"]) {
+        Err(error) => panic!(error.to_string()),
+        Ok(found) => {
+            assert_eq!(found.len(), 2);
+            assert_eq!(found["A"]["id"].as_slice(), "A");
+            assert_eq!(found["C"]["id"].as_slice(), "C");
+            assert!(!found.contains_key("B"));
+        }
+    }
+    assert!(requests.recv().unwrap().ends_with("g.V(\"A\",\"B\",\"C\").All()"));
+
+    assert!(Graph::default().unwrap().find_nodes(&[]).unwrap().is_empty());
+
+    // a lookup is not a query result, so the validators don't reject it
+    let (port, _) = serve("{\"result\":[]}");
+    let graph = graph_at(port).with_result_validator(|nodes| if nodes.is_empty() { Err("empty".to_string()) } else { Ok(()) });
+    assert!(graph.find_nodes(&["Z"]).unwrap().is_empty());
+
+}
+
+// == Case-insensitive lookup ==
+
+#[test]
+fn test_find_ci() {
+
+    let (port, requests) = serve("{\"result\":[{\"id\":\"/en/casablanca_1942\"},{\"id\":\"/en/casablanca_remake\"}]}");
+    match graph_at(port).find_ci("name", "casablanca") {
+        Err(error) => panic!(error.to_string()),
+        Ok(nodes) => assert_eq!(nodes.ids(), vec!["/en/casablanca_1942", "/en/casablanca_remake"])
+    }
+    assert!(requests.recv().unwrap()
+                    .ends_with("g.V().Filter(regex(\"(?i)^casablanca$\")).In(\"name\").Unique().All()"));
+
+    // the value is matched as it is, not as a pattern
+    let (port, requests) = serve("{\"result\":[]}");
+    graph_at(port).find_ci("name", "Dr. Strangelove (1964)").unwrap();
+    assert!(requests.recv().unwrap()
+                    .ends_with("g.V().Filter(regex(\"(?i)^Dr\\\\. Strangelove \\\\(1964\\\\)$\")).In(\"name\").Unique().All()"));
+
+}
+
+// == Method ==
+
+#[test]
+fn test_method() {
+
+    let (port, request) = serve("{\"result\":[]}");
+    graph_at(port).find(vertex![ AnyNode => All ]).unwrap();
+    let request = request.recv().unwrap();
+    assert!(request.starts_with("POST /api/v1/query/gremlin HTTP/1.1"));
+    assert!(request.ends_with("g.V().All()"));
+
+    let (port, request) = serve("{\"result\":[]}");
+    graph_at(port).with_method(Method::Get).find(vertex![ AnyNode => All ]).unwrap();
+    assert!(request.recv().unwrap().starts_with("GET /api/v1/query/gremlin?query=g.V%28%29.All%28%29 HTTP/1.1"));
+
+    let long_name: String = (0..MAX_GET_URL_LENGTH).map(|_| 'a').collect();
+    let (port, request) = serve("{\"result\":[]}");
+    graph_at(port).with_method(Method::Get).find(vertex![ Node(long_name.as_slice()) => All ]).unwrap();
+    let request = request.recv().unwrap();
+    assert!(request.starts_with("POST /api/v1/query/gremlin HTTP/1.1"));
+    assert!(request.ends_with(".All()"));
+
+}
+
+// == Value transforms ==
+
+#[test]
+fn test_value_transform() {
+
+    let (port, _) = serve("{\"result\":[{\"id\":\"/en/casablanca_1942\",\"thumbnail\":\"gnp.nac\"}]}");
+    let graph = graph_at(port).with_value_transform("thumbnail", |value| value.chars().rev().collect());
+    match graph.find(vertex![ AnyNode => All ]) {
+        Err(error) => panic!(error.to_string()),
+        Ok(cayley::Nodes(nodes)) => {
+            assert_eq!(nodes[0]["thumbnail"].as_slice(), "can.png");
+            assert_eq!(nodes[0]["id"].as_slice(), "/en/casablanca_1942");
+        }
+    }
+
+}
+
+// == Batches ==
+
+#[test]
+fn test_find_many() {
+
+    fn batch() -> Vec<cayley::path::CompiledQuery> {
+        vec![ vertex![ Node("A") => All ], vertex![ Node("B") => All ], vertex![ Node("C") => All ] ]
+    }
+    let responses = vec!["{\"result\":[{\"id\":\"A\"}]}",
+                         "{\"error\":\"something went wrong\"}",
+                         "{\"result\":[{\"id\":\"C\"}]}"];
+
+    let (port, _) = serve_all(responses.clone());
+    let results = graph_at(port).find_many(batch(), CollectAll);
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert!(results[2].is_ok());
+
+    let (port, _) = serve_all(responses);
+    let results = graph_at(port).find_many(batch(), AbortOnError);
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+
+}
+
+#[test]
+fn test_exec_many() {
+
+    let (port, requests) = serve("{\"result\":[{\"id\":\"A\"},{\"__batch_end\":\"0\"},\
+                                               {\"__batch_end\":\"1\"},\
+                                               {\"id\":\"C\"},{\"id\":\"D\"},{\"__batch_end\":\"2\"}]}");
+    let results = graph_at(port).exec_many(&[ vertex![ Node("A") => All ],
+                                              vertex![ Node("B") => All ],
+                                              vertex![ Node("C") -> OutP(Predicate("follows")) => All ] ]).unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].0.len(), 1);
+    assert!(results[1].0.is_empty());
+    assert_eq!(results[2].0.len(), 2);
+    assert_eq!(results[2].0[1]["id"].as_slice(), "D");
+    assert!(requests.recv().unwrap().ends_with("g.V(\"A\").All();g.Emit({\"__batch_end\":\"0\"});\
+                                                g.V(\"B\").All();g.Emit({\"__batch_end\":\"1\"});\
+                                                g.V(\"C\").Out(\"follows\").All();g.Emit({\"__batch_end\":\"2\"});"));
+
+    match graph_at(port).exec_many(&[ vertex![ Node("A") => Count ] ]) {
+        Err(cayley::Error::ExpectationMismatch(_)) => (),
+        _ => panic!("should not batch a query not returning nodes")
+    }
+
+    let (port, _) = serve("{\"result\":[{\"id\":\"A\"}]}");
+    match graph_at(port).exec_many(&[ vertex![ Node("A") => All ] ]) {
+        Err(cayley::Error::ResponseParseFailed) => (),
+        _ => panic!("should fail when the markers are missing")
+    }
+
+    let (port, _) = serve("{\"result\":[{\"id\":\"A\",\"__batch_end\":\"0\"},{\"__batch_end\":\"1\"},\
+                                         {\"__batch_end\":\"0\"},{\"__batch_end\":\"1\"}]}");
+    let results = graph_at(port).exec_many(&[ vertex![ Node("A") => All ], vertex![ Node("B") => All ] ]).unwrap();
+    assert_eq!(results[0].len(), 2);
+    assert_eq!(results[0].ids(), vec!["A"]);
+    assert!(results[1].is_empty());
+
+    let (port, _) = serve("{\"result\":[{\"id\":\"A\"},{\"__batch_end\":\"0\"}]}");
+    match graph_at(port).with_result_validator(|nodes| if nodes.is_empty() { Err("empty".to_string()) } else { Ok(()) })
+                        .exec_many(&[ vertex![ Node("A") => All ] ]) {
+        Ok(results) => assert_eq!(results[0].ids(), vec!["A"]),
+        Err(error) => panic!(error.to_string())
+    }
+
+}
+
+#[test]
+fn test_exec_many_shared_morphism() {
+
+    let follows = morphism![ "follows" -> OutP(Predicate("follows")) ];
+    let (port, requests) = serve("{\"result\":[{\"__batch_end\":\"0\"},{\"__batch_end\":\"1\"}]}");
+    graph_at(port).exec_many(&[ vertex![ Node("A") -> Follow(&follows) => All ],
+                                vertex![ Node("B") -> Follow(&follows) => All ] ]).unwrap();
+    assert!(requests.recv().unwrap().ends_with("\n\r\nvar follows = g.M().Out(\"follows\");\
+                                                g.V(\"A\").Follow(follows).All();g.Emit({\"__batch_end\":\"0\"});\
+                                                g.V(\"B\").Follow(follows).All();g.Emit({\"__batch_end\":\"1\"});"));
+
+    let other_follows = morphism![ "follows" -> InP(Predicate("follows")) ];
+    match graph_at(port).exec_many(&[ vertex![ Node("A") -> Follow(&follows) => All ],
+                                      vertex![ Node("B") -> Follow(&other_follows) => All ] ]) {
+        Err(cayley::Error::QueryCompilationFailed(None)) => (),
+        _ => panic!("should not batch different Morphisms with the same name")
+    }
+
+}
+
+// == Single results ==
+
+#[test]
+fn test_find_scalar() {
+
+    let (port, _) = serve_all(vec!["{\"result\":null}",
+                                   "{\"result\":[{\"id\":\"Casablanca\"}]}",
+                                   "{\"result\":[{\"id\":\"Casablanca\"}]}",
+                                   "{\"result\":[{\"id\":\"Casablanca\"},{\"id\":\"Casablanca (1942)\"}]}"]);
+    let graph = graph_at(port);
+    fn query() -> cayley::path::CompiledQuery { vertex![ Node("/en/casablanca_1942") -> OutP(Predicate("name")) => All ] }
+
+    assert_eq!(graph.find_scalar(query(), "id").unwrap(), None);
+    assert_eq!(graph.find_scalar(query(), "id").unwrap(), Some("Casablanca".to_string()));
+    assert_eq!(graph.find_scalar(query(), "label").unwrap(), None);
+    match graph.find_scalar(query(), "id") {
+        Err(TooManyResults(count)) => assert_eq!(count, 2),
+        _ => panic!("two results should fail")
+    }
+
+}
+
+// == Result validators ==
+
+#[test]
+fn test_result_validator() {
+
+    fn unique_ids(nodes: &cayley::Nodes) -> Result<(), String> {
+        let mut ids: Vec<&str> = nodes.0.iter().filter_map(|node| node.primary()).collect();
+        let count = ids.len();
+        ids.sort();
+        ids.dedup();
+        if ids.len() == count { Ok(()) } else { Err("ids are not unique".to_string()) }
+    }
+
+    let (port, _) = serve_all(vec!["{\"result\":[{\"id\":\"A\"},{\"id\":\"B\"}]}",
+                                   "{\"result\":[{\"id\":\"A\"},{\"id\":\"A\"}]}"]);
+    let graph = graph_at(port).with_result_validator(unique_ids);
+
+    assert_eq!(graph.find(vertex![ AnyNode => All ]).unwrap().0.len(), 2);
+    match graph.find(vertex![ AnyNode => All ]) {
+        Err(ResultRejected(explanation)) => assert_eq!(explanation.as_slice(), "ids are not unique"),
+        _ => panic!("result should be rejected")
+    }
+
+}
+
+// == Limit parameter ==
+
+#[test]
+fn test_find_capped() {
+
+    // this server ignores the parameter and returns more nodes than the cap
+    let (port, request) = serve("{\"result\":[{\"id\":\"A\"},{\"id\":\"B\"},{\"id\":\"C\"}],\"cursor\":\"page-2\"}");
+    match graph_at(port).find_capped(vertex![ AnyNode => GetLimit(10) ], 2) {
+        Err(error) => panic!(error.to_string()),
+        Ok(nodes) => assert_eq!(nodes.ids(), vec!["A", "B"])
+    }
+    let request = request.recv().unwrap();
+    assert!(request.starts_with("POST /api/v1/query/gremlin?limit=2 HTTP/1.1"));
+    assert!(request.ends_with("g.V().GetLimit(10)"));
+
+}
+
+// == Query recording ==
+
+#[test]
+fn test_last_query() {
+
+    let (port, _) = serve("{\"result\":[]}");
+    let graph = graph_at(port);
+    graph.find(vertex![ AnyNode => All ]).unwrap();
+    assert_eq!(graph.last_query(), None);
+
+    // every method records the query, with the saved Morphisms declared before it
+    let (port, _) = serve_all(vec!["{\"result\":3}", PROBED, "{\"result\":[\"A\"]}"]);
+    let mut graph = graph_at(port).with_query_recording().with_capability_probe();
+    graph.save(morphism![ "follows" -> OutP(Predicate("follows")) ]);
+    assert_eq!(graph.last_query(), None);
+    graph.count(vertex![ AnyNode -> OutP(Predicate("follows")) => Count ]).unwrap();
+    assert_eq!(graph.last_query(),
+               Some("var follows = g.M().Out(\"follows\");g.V().Out(\"follows\").Count()".to_string()));
+    graph.find_names(vertex![ Node("B") -> InP(Predicate("follows")) => ToArray ]).unwrap();
+    assert_eq!(graph.last_query(),
+               Some("var follows = g.M().Out(\"follows\");g.V(\"B\").In(\"follows\").ToArray()".to_string()));
+
+    // in debug builds, a malformed query is recorded, but not sent
+    if cfg!(debug_assertions) {
+        let graph = Graph::default().unwrap().with_query_recording();
+        match graph.exec_raw("g.V(\"foo\").All(".to_string()) {
+            Err(cayley::Error::QueryCompilationFailed(Some(_))) => {},
+            _ => panic!("a malformed query should not be sent")
+        }
+        assert_eq!(graph.last_query(), Some("g.V(\"foo\").All(".to_string()));
+    }
+
+}
+
+// == Partial results ==
+
+#[test]
+fn test_partial_result() {
+
+    let (port, _) = serve("{\"error\":\"query timed out\",\"result\":[{\"id\":\"A\"},{\"id\":\"B\"}]}");
+    match graph_at(port).find(vertex![ AnyNode => All ]) {
+        Err(PartialResult { message, nodes: cayley::Nodes(nodes) }) => {
+            assert_eq!(message.as_slice(), "query timed out");
+            assert_eq!(nodes.len(), 2);
+            assert_eq!(nodes[1]["id"].as_slice(), "B");
+        },
+        Err(error) => panic!(error.to_string()),
+        Ok(_) => panic!("partial result should be an error")
+    }
+
+    let (port, _) = serve("{\"error\":\"query timed out\",\"result\":null}");
+    match graph_at(port).find(vertex![ AnyNode => All ]) {
+        Err(PartialResult { .. }) | Ok(_) => panic!("no partial result expected"),
+        Err(_) => {}
+    }
+
+}
+
+// == Predicate tags ==
+
+#[test]
+fn test_predicate_tag() {
+
+    let (port, requests) = serve("{\"result\":[{\"id\":\"B\",\"via\":\"follows\"},\
+                                              {\"id\":\"cool_person\",\"via\":\"status\"}]}");
+    match graph_at(port).find(vertex![ Node("D") -> Out(Predicates(vec!("follows", "status")), Tag("via")) => All ]) {
+        Err(error) => panic!(error.to_string()),
+        Ok(cayley::Nodes(nodes)) => {
+            assert_eq!(nodes[0]["via"].as_slice(), "follows");
+            assert_eq!(nodes[1]["via"].as_slice(), "status");
+        }
+    }
+    assert!(requests.recv().unwrap().ends_with("g.V(\"D\").Out([\"follows\",\"status\"],\"via\").All()"));
+
+}
+
+// == Result rows ==
+
+#[test]
+fn test_find_rows() {
+
+    let (port, requests) = serve("{\"result\":[{\"id\":\"B\",\"source\":\"D\",\"label\":\"graph-1\"},\
+                                              {\"id\":\"C\",\"source\":\"D\"}]}");
+    let rows = graph_at(port).find_rows(vertex![ Node("D") -> As(Tag("source")) -> OutP(Predicate("follows")) => All ])
+                             .unwrap();
+    assert!(requests.recv().unwrap().ends_with("g.V(\"D\").As(\"source\").Out(\"follows\").All()"));
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].id(), Some("B"));
+    assert_eq!(rows[0].tag("source"), Some("D"));
+    assert_eq!(rows[0].tags(), vec![("label", "graph-1"), ("source", "D")]);
+    assert_eq!(rows[1].id(), Some("C"));
+    assert_eq!(rows[1].tag("label"), None);
+
+    let (port, _) = serve("{\"error\":\"boom\"}");
+    match graph_at(port).find_rows(vertex![ AnyNode => All ]) {
+        Err(ServerError(message)) => assert_eq!(message, "boom"),
+        other => panic!("expected ServerError, got {:?}", other)
+    }
+
+}
+
+// == JSON ==
+
+#[test]
+fn test_find_json() {
+
+    let (port, _) = serve("{\"result\":[{\"id\":\"B\",\"actors\":[{\"id\":\"A\"}],\"rating\":7.5}]}");
+    let json = graph_at(port).find_json(vertex![ AnyNode => All ]).unwrap();
+    let first = &json.as_array().unwrap()[0];
+    assert_eq!(first.find("id").and_then(|id| id.as_string()), Some("B"));
+    assert_eq!(first.find_path(&["actors"]).and_then(|actors| actors.as_array()).map(|actors| actors.len()), Some(1));
+    assert_eq!(first.find("rating").and_then(|rating| rating.as_f64()), Some(7.5));
+
+}
+
+// == Numeric fields ==
+
+#[test]
+fn test_find_numeric() {
+
+    let (port, _) = serve("{\"result\":[{\"id\":\"Casablanca\",\"year\":\"1942\",\"rating\":\"n/a\",\"code\":\"7\"}]}");
+    let graph = graph_at(port).with_numeric_fields(&["year", "rating"]);
+    let nodes = graph.find_numeric(vertex![ AnyNode => All ]).unwrap();
+    assert_eq!(nodes[0]["year"], Num(1942.0));
+    assert_eq!(nodes[0]["rating"], Str("n/a".to_string()));
+    assert_eq!(nodes[0]["code"], Str("7".to_string()));
+    assert_eq!(nodes[0]["id"], Str("Casablanca".to_string()));
+
+}
+
+// == Prefix size ==
+
+#[test]
+fn test_max_prefix_bytes() {
+
+    let follows = morphism![ "follows" -> OutP(Predicate("follows")) ];
+    let prefix_len = "var follows = g.M().Out(\"follows\");".len();
+
+    // the query is rejected before sending, so there's no server to answer it
+    match Graph::default().unwrap().with_max_prefix_bytes(prefix_len - 1)
+                          .find(vertex![ Node("C") -> Follow(&follows) => All ]) {
+        Err(PrefixTooLarge(size)) => assert_eq!(size, prefix_len),
+        _ => panic!("prefix should be rejected")
+    }
+
+    let (port, _) = serve("{\"result\":[]}");
+    graph_at(port).with_max_prefix_bytes(prefix_len)
+                  .find(vertex![ Node("C") -> Follow(&follows) => All ]).unwrap();
+
+}
+
+// == Root name ==
+
+#[test]
+fn test_root_name() {
+
+    let follows = morphism![ "follows" -> OutP(Predicate("follows")) ];
+
+    let (port, requests) = serve("{\"result\":[]}");
+    graph_at(port).with_root_name(DEFAULT_ROOT_NAME).find(vertex![ Node("C") -> Follow(&follows) => All ]).unwrap();
+    assert!(requests.recv().unwrap().ends_with("\r\n\r\nvar follows = g.M().Out(\"follows\");g.V(\"C\").Follow(follows).All()"));
+
+    let follows = Morphism::compile_reuse_at("graph", "follows", vec![ OutP(Predicate("follows")) ].into_boxed_slice()).unwrap();
+    let (port, requests) = serve("{\"result\":[]}");
+    graph_at(port).with_root_name("graph")
+                  .find(Vertex::compile_query_at("graph", Node("C"), vec![ Follow(&follows) ].into_boxed_slice(), All).unwrap())
+                  .unwrap();
+    assert!(requests.recv().unwrap().ends_with("\r\n\r\nvar follows = graph.M().Out(\"follows\");graph.V(\"C\").Follow(follows).All()"));
+
+    // the queries the Graph makes itself are compiled for its root name
+    let (port, requests) = serve("{\"result\":[]}");
+    graph_at(port).with_root_name("graph").relationships("C").unwrap();
+    assert!(requests.recv().unwrap().ends_with("\r\n\r\ngraph.V(\"C\").Out(null,\"predicate\").All()"));
+
+    // a query compiled for `g` is not sent to a graph bound to another name
+    match graph_at(1).with_root_name("graph").find(vertex![ Node("C") => All ]) {
+        Err(RootNameMismatch(root_name)) => assert_eq!(root_name, "graph"),
+        other => panic!("expected RootNameMismatch, got {:?}", other)
+    }
+
+}
+
+// == Writing ==
+
+#[test]
+fn test_write() {
+
+    let quads = vec![ Quad::new("/zh/new_movie", "name", "New Movie").unwrap(),
+                      Quad::with_label("A", "follows", "B", "social").unwrap() ];
+
+    let (port, requests) = serve("{\"result\":\"Successfully wrote 2 quads.\"}");
+    assert_eq!(graph_at(port).write(&quads).unwrap(), 2);
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("POST /api/v1/write HTTP/1.1"));
+    assert!(request.contains("Content-Type: application/json"));
+    assert!(request.ends_with("[{\"subject\":\"/zh/new_movie\",\"predicate\":\"name\",\"object\":\"New Movie\"},\
+                               {\"subject\":\"A\",\"predicate\":\"follows\",\"object\":\"B\",\"label\":\"social\"}]"));
+
+    // writes are always sent with `POST`, whatever method is set for queries
+    let (port, requests) = serve("{\"error\":\"Couldn't parse quads\"}");
+    match graph_at(port).with_method(Method::Get).write(&quads) {
+        Err(ServerError(ref explanation)) => assert_eq!(explanation.as_slice(), "Couldn't parse quads"),
+        _ => panic!("error from Cayley should fail the write")
+    }
+    assert!(requests.recv().unwrap().starts_with("POST /api/v1/write HTTP/1.1"));
+
+}
+
+// == Deleting ==
+
+#[test]
+fn test_delete() {
+
+    let quads = vec![ Quad::new("/zh/new_movie", "name", "New Movie").unwrap() ];
+
+    let (port, requests) = serve("{\"result\":\"Successfully deleted 1 quads.\"}");
+    assert_eq!(graph_at(port).delete(&quads).unwrap(), 1);
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("POST /api/v1/delete HTTP/1.1"));
+    assert!(request.ends_with("[{\"subject\":\"/zh/new_movie\",\"predicate\":\"name\",\"object\":\"New Movie\"}]"));
+
+    let (port, _) = serve("{\"error\":\"quad does not exist\"}");
+    match graph_at(port).delete(&quads) {
+        Err(QuadNotFound(explanation)) => assert_eq!(explanation.as_slice(), "quad does not exist"),
+        _ => panic!("deleting a missing quad should fail with QuadNotFound")
+    }
+
+}
+
+// == Timeout ==
+
+#[test]
+fn test_timeout() {
+
+    let port = hang(Duration::from_secs(2));
+    let timeout = Duration::from_millis(100);
+    match graph_at(port).with_timeout(timeout).find(vertex![ AnyNode => All ]) {
+        Err(RequestTimedOut(after)) => assert_eq!(after, timeout),
+        Err(error) => panic!(error.to_string()),
+        Ok(_) => panic!("request should time out")
+    }
+
+    let (port, _) = serve("{\"result\":[]}");
+    graph_at(port).with_timeout(timeout).find(vertex![ AnyNode => All ]).unwrap();
+
+}
+
+// == TLS ==
+
+#[test]
+fn test_new_secure() {
+
+    // the first bytes a client sends are enough to tell TLS from plain HTTP
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (sender, received) = channel();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut first = [0u8; 1];
+        stream.read(&mut first).unwrap();
+        sender.send(first[0]).unwrap();
+    });
+    let graph = Graph::new_secure("127.0.0.1", port as i32, V1).unwrap().with_timeout(Duration::from_secs(1));
+    assert!(graph.find(vertex![ AnyNode => All ]).is_err());
+    // 0x16 starts a TLS handshake record
+    assert_eq!(received.recv().unwrap(), 0x16);
+
+}
+
+// == Typed results ==
+
+#[derive(RustcDecodable)]
+struct Movie {
+    id: String,
+    name: String
+}
+
+#[test]
+fn test_find_typed() {
+
+    let (port, _) = serve("{\"result\":[{\"id\":\"/en/casablanca_1942\",\"name\":\"Casablanca\"},\
+                                          {\"id\":\"/en/the_maltese_falcon\",\"name\":\"The Maltese Falcon\"}]}");
+    let movies: Vec<Movie> = graph_at(port).find_typed(vertex![ AnyNode => All ]).unwrap();
+    assert_eq!(movies.len(), 2);
+    assert_eq!(movies[0].id.as_slice(), "/en/casablanca_1942");
+    assert_eq!(movies[1].name.as_slice(), "The Maltese Falcon");
+
+    let (port, _) = serve("{\"result\":[{\"id\":\"/en/casablanca_1942\"}]}");
+    match graph_at(port).find_typed::<Movie>(vertex![ AnyNode => All ]) {
+        Err(DecodingFailed(..)) => {},
+        _ => panic!("an item with no `name` should fail to decode")
+    }
+
+    let (port, _) = serve("{\"error\":\"TypeError: Object has no method 'Foo'\"}");
+    match graph_at(port).find_typed::<Movie>(vertex![ AnyNode => All ]) {
+        Err(ServerError(explanation)) => assert_eq!(explanation.as_slice(), "TypeError: Object has no method 'Foo'"),
+        _ => panic!("an error from Cayley should be returned as it is")
+    }
+
+    match Graph::default().unwrap().find_typed::<Movie>(vertex![ AnyNode => Count ]) {
+        Err(ExpectationMismatch(cayley::path::Expectation::CountValue)) => {},
+        _ => panic!("a query with Count() should not be accepted")
+    }
+
+}
+
+// == Names ==
+
+#[test]
+fn test_find_names() {
+
+    let (port, requests) = serve_all(vec![PROBED, "{\"result\":[\"A\",\"C\",\"D\"]}"]);
+    let names = graph_at(port).with_capability_probe()
+                              .find_names(vertex![ Node("B") -> InP(Predicate("follows")) => ToArray ]).unwrap();
+    assert_eq!(names, vec!["A".to_string(), "C".to_string(), "D".to_string()]);
+    assert!(requests.recv().unwrap().ends_with("\r\n\r\ng.V().ToArray()"));
+    assert!(requests.recv().unwrap().ends_with("g.V(\"B\").In(\"follows\").ToArray()"));
+
+    // the same check as for `exec`: without probing, `ToArray()` is not sent
+    match Graph::default().unwrap().find_names(vertex![ Node("B") -> InP(Predicate("follows")) => ToArray ]) {
+        Err(ExpectationNotSupported(cayley::path::Expectation::NameSequence)) => {},
+        _ => panic!("ToArray() should be rejected")
+    }
+
+    match Graph::default().unwrap().find_names(vertex![ Node("B") -> InP(Predicate("follows")) => All ]) {
+        Err(ExpectationMismatch(NodeSequence)) => {},
+        _ => panic!("a query with All() should not be accepted")
+    }
+
+}
+
+// == Single value ==
+
+#[test]
+fn test_find_one_to_value() {
+
+    let (port, requests) = serve_all(vec![PROBED, "{\"result\":{\"id\":\"/en/casablanca_1942\"}}"]);
+    let found = graph_at(port).with_capability_probe()
+                              .find_one(vertex![ Node("Casablanca") -> InP(Predicate("name")) => ToValue ]).unwrap();
+    assert_eq!(found.unwrap()["id"].as_slice(), "/en/casablanca_1942");
+    requests.recv().unwrap();
+    assert!(requests.recv().unwrap().ends_with("g.V(\"Casablanca\").In(\"name\").ToValue()"));
+
+    let (port, _) = serve_all(vec![PROBED, "{\"result\":null}"]);
+    assert!(graph_at(port).with_capability_probe()
+                          .find_one(vertex![ Node("Casablanca") -> InP(Predicate("name")) => ToValue ])
+                          .unwrap().is_none());
+
+    // Cayley not supporting `ToValue()` is asked only once
+    let (port, _) = serve("{\"error\":\"ToValue is not supported\"}");
+    let graph = graph_at(port).with_capability_probe();
+    for _ in 0..2 {
+        match graph.find_one(vertex![ Node("Casablanca") -> InP(Predicate("name")) => ToValue ]) {
+            Err(ExpectationNotSupported(SingleNode)) => {},
+            _ => panic!("ToValue() should be rejected")
+        }
+    }
+
+}
+
+#[test]
+fn test_ping() {
+
+    let (port, request) = serve("{\"result\":null}");
+    assert!(graph_at(port).ping().is_ok());
+    assert!(request.recv().unwrap().ends_with("g.V().Limit(0).All()"));
+
+    let port = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    match graph_at(port).ping() {
+        Err(RequestFailed(..)) => {},
+        _ => panic!("nobody listens at the port, so ping should fail")
+    }
+
+    // the validators are for the results of queries, not for the empty result of ping
+    let (port, request) = serve("{\"result\":null}");
+    let graph = graph_at(port).with_root_name("graph")
+                              .with_result_validator(|nodes| if nodes.is_empty() { Err("empty".to_string()) } else { Ok(()) });
+    assert!(graph.ping().is_ok());
+    assert!(request.recv().unwrap().ends_with("\r\n\r\ngraph.V().Limit(0).All()"));
+
+    let (port, _) = serve("{\"error\":\"something went wrong\"}");
+    match graph_at(port).ping() {
+        Err(cayley::Error::ServerError(explanation)) => assert_eq!(explanation.as_slice(), "something went wrong"),
+        _ => panic!("Cayley failed, so ping should fail")
+    }
+
+}
+
+// == Tags ==
+
+#[test]
+fn test_find_tags() {
+
+    let (port, requests) = serve_all(vec![PROBED, "{\"result\":[{\"source\":\"A\",\"target\":\"B\"},{\"source\":\"C\",\"target\":\"B\"}]}"]);
+    let tags = graph_at(port).with_capability_probe().find_tags(vertex![ AnyNode -> As(Tag("source")) -> Out(Predicate("follows"), Tag("target"))
+                                                 => TagArray ]).unwrap();
+    assert_eq!(tags.len(), 2);
+    assert_eq!(tags[0]["source"].as_slice(), "A");
+    assert_eq!(tags[1]["target"].as_slice(), "B");
+    requests.recv().unwrap();
+    assert!(requests.recv().unwrap().ends_with(".TagArray()"));
+
+    let (port, _) = serve_all(vec![PROBED, "{\"result\":{\"source\":\"A\"}}"]);
+    let tag = graph_at(port).with_capability_probe()
+                            .find_tag(vertex![ Node("A") -> As(Tag("source")) => TagValue ]).unwrap();
+    assert_eq!(tag.unwrap()["source"].as_slice(), "A");
+
+    let (port, _) = serve_all(vec![PROBED, "{\"result\":null}"]);
+    assert!(graph_at(port).with_capability_probe()
+                          .find_tag(vertex![ Node("A") -> As(Tag("source")) => TagValue ]).unwrap().is_none());
+
+}
+
+// == Count ==
+
+#[test]
+fn test_count() {
+
+    let (port, requests) = serve("{\"result\":11}");
+    assert_eq!(graph_at(port).count(vertex![ AnyNode -> OutP(Predicate("follows")) => Count ]).unwrap(), 11);
+    assert!(requests.recv().unwrap().ends_with("g.V().Out(\"follows\").Count()"));
+
+    match Graph::default().unwrap().count(vertex![ AnyNode -> OutP(Predicate("follows")) => All ]) {
+        Err(ExpectationMismatch(NodeSequence)) => {},
+        _ => panic!("a query with All() should not be counted")
+    }
+
+}
+
+// == MQL ==
+
+#[test]
+fn test_find_mql() {
+
+    let (port, requests) = serve("{\"result\":[{\"id\":\"/en/casablanca\",\"name\":\"Casablanca\",\"year\":1942}]}");
+    let found = graph_at(port).find_mql("[{\"id\":null,\"name\":\"Casablanca\"}]").unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0]["id"].as_slice(), "/en/casablanca");
+    assert_eq!(found[0]["year"].as_slice(), "1942");
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("POST /api/v1/query/mql"));
+    assert!(request.ends_with("[{\"id\":null,\"name\":\"Casablanca\"}]"));
+
+}
+
+// == Shape ==
+
+#[test]
+fn test_shape() {
+
+    let (port, requests) = serve("{\"nodes\":[{\"id\":1,\"tags\":null,\"values\":[\"Casablanca\"],\
+                                    \"is_link_node\":false,\"is_fixed\":true},\
+                                   {\"id\":2,\"tags\":[\"name\"],\"values\":null,\
+                                    \"is_link_node\":false,\"is_fixed\":false}],\
+                                  \"links\":[{\"source\":1,\"target\":2,\"link_node\":3}]}");
+    let shape = graph_at(port).shape(vertex![ Node("Casablanca") -> Out(Predicate("name"), Tag("name")) => All ]).unwrap();
+    assert_eq!(shape.nodes.len(), 2);
+    assert_eq!(shape.nodes[0].values, Some(vec!["Casablanca".to_string()]));
+    assert_eq!(shape.nodes[1].tags, Some(vec!["name".to_string()]));
+    assert_eq!((shape.links[0].source, shape.links[0].target), (1, 2));
+    assert!(requests.recv().unwrap().starts_with("POST /api/v1/shape/gremlin"));
+
+    let (port, _) = serve("{\"nodes\":[{\"id\":1,\"values\":[\"Casablanca\"],\"is_link_node\":false,\"is_fixed\":true}]}");
+    assert!(graph_at(port).shape(vertex![ Node("Casablanca") => All ]).unwrap().links.is_empty());
+
+}
+
+// == Server Errors ==
+
+#[test]
+fn test_server_error() {
+
+    let (port, _) = serve("{\"error\":\"TypeError: Object has no method 'Foo'\"}");
+    match graph_at(port).find(vertex![ AnyNode => All ]) {
+        Err(error @ ServerError(_)) =>
+            assert_eq!(error.to_string().as_slice(), "Cayley returned an error: TypeError: Object has no method 'Foo'"),
+        _ => panic!("an error from Cayley should be returned as it is")
+    }
+
+    let (port, _) = serve("{\"error\":\"TypeError: Object has no method 'Foo'\"}");
+    match graph_at(port).count(vertex![ AnyNode => Count ]) {
+        Err(ServerError(ref explanation)) => assert_eq!(explanation.as_slice(), "TypeError: Object has no method 'Foo'"),
+        _ => panic!("an error from Cayley should be returned as it is")
+    }
+
+}
+
+// == Error Display ==
+
+#[test]
+fn test_error_display() {
+
+    let port = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    match graph_at(port).find(vertex![ Node("foo") => All ]) {
+        Err(error @ RequestFailed(..)) =>
+            assert_eq!(error.to_string().as_slice(), "Request failed, Query: g.V(\"foo\").All()"),
+        _ => panic!("nobody listens at the port, so the request should fail")
+    }
+
+    match Graph::new("local host", 64210, V1) {
+        Err(error) => assert_eq!(error.to_string().as_slice(),
+                                 "Invalid Url, Url: http://local host:64210/api/v1/query/gremlin"),
+        Ok(_) => panic!("a host with a space should not be accepted")
+    }
+
+}
+
+// == ForEach ==
+
+#[test]
+fn test_find_emitted() {
+
+    let (port, requests) = serve("{\"result\":[{\"film\":\"Casablanca\"},\"Humphrey Bogart\"]}");
+    let emitted = graph_at(port).find_emitted(vertex![ Node("Casablanca")
+                                                       => ForEach("g.Emit({ film: d.id })") ]).unwrap();
+    assert_eq!(emitted.len(), 2);
+    assert_eq!(emitted[1], Value::Text("Humphrey Bogart".to_string()));
+    assert!(requests.recv().unwrap().ends_with("g.V(\"Casablanca\").ForEach(function(d){ g.Emit({ film: d.id }) })"));
+
+}
+
+// == Raw ==
+
+#[test]
+fn test_exec_raw() {
+
+    let body = "{\"result\":[{\"id\":\"foo\"}],\"unknown\":42}";
+    let (port, requests) = serve(body);
+    let raw = graph_at(port).exec_raw("g.V(\"foo\").Unknown()".to_string()).unwrap();
+    assert_eq!(String::from_utf8(raw).unwrap().as_slice(), body);
+    assert!(requests.recv().unwrap().ends_with("g.V(\"foo\").Unknown()"));
+
+}
+
+// == Retries ==
+
+#[test]
+fn test_retries() {
+
+    let (port, requests) = serve_all(vec![DROP, DROP, "{\"result\":[{\"id\":\"foo\"}]}"]);
+    match graph_at(port).with_retries(3).find(vertex![ Node("foo") => All ]) {
+        Err(error) => panic!(error.to_string()),
+        Ok(cayley::Nodes(nodes)) => assert_eq!(nodes.len(), 1)
+    }
+    assert!(requests.recv().unwrap().ends_with("g.V(\"foo\").All()"));
+
+    let (port, _) = serve_all(vec![DROP, DROP, "{\"result\":[{\"id\":\"foo\"}]}"]);
+    match graph_at(port).with_retries(1).find(vertex![ Node("foo") => All ]) {
+        Err(RequestFailed(..)) => {},
+        _ => panic!("the last error should be returned when all the attempts failed")
+    }
+
+    let (port, _) = serve_all(vec!["{\"error\":\"TypeError\"}", "{\"result\":[{\"id\":\"foo\"}]}"]);
+    match graph_at(port).with_retries(3).find(vertex![ Node("foo") => All ]) {
+        Err(ServerError(..)) => {},
+        _ => panic!("errors from Cayley should not be retried")
+    }
+
+}
+
+// == HTTP Status ==
+
+#[test]
+fn test_http_status() {
+
+    let (port, _) = serve("HTTP/1.1 500 Internal Server Error\r\nContent-Length: 12\r\nConnection: close\r\n\r\nCayley fell!");
+    match graph_at(port).find(vertex![ AnyNode => All ]) {
+        Err(HttpStatus(status, ref body)) => {
+            assert_eq!(status, StatusCode::InternalServerError);
+            assert_eq!(body.as_slice(), "Cayley fell!");
+        },
+        _ => panic!("a response with 500 status should fail the query")
+    }
+
+}
+
+// == Pages ==
+
+#[test]
+fn test_find_paged() {
+
+    let (port, requests) = serve_all(vec!["{\"result\":[{\"id\":\"A\"},{\"id\":\"B\"}]}",
+                                          "{\"result\":[{\"id\":\"C\"}]}"]);
+    let graph = graph_at(port);
+    let pages: Vec<usize> = graph.find_paged(vertex![ AnyNode -> OutP(Predicate("follows")) ], 2)
+                                 .map(|page| match page.unwrap() { cayley::Nodes(nodes) => nodes.len() })
+                                 .collect();
+    assert_eq!(pages, vec![2, 1]);
+    assert!(requests.recv().unwrap().ends_with("g.V().Out(\"follows\").Skip(0).Limit(2).All()"));
+    assert!(requests.recv().unwrap().ends_with("g.V().Out(\"follows\").Skip(2).Limit(2).All()"));
+
+    // a failed page ends the pages
+    let (port, _) = serve_all(vec!["{\"result\":[{\"id\":\"A\"},{\"id\":\"B\"}]}", DROP]);
+    let graph = graph_at(port);
+    let mut pages = graph.find_paged(vertex![ AnyNode -> OutP(Predicate("follows")) ], 2);
+    assert!(pages.next().unwrap().is_ok());
+    assert!(pages.next().unwrap().is_err());
+    assert!(pages.next().is_none());
+
+}
+
+// == First ==
+
+#[test]
+fn test_find_first() {
+
+    let (port, requests) = serve("{\"result\":[{\"id\":\"A\"}]}");
+    let first = graph_at(port).find_one(vertex![ AnyNode => First ]).unwrap();
+    assert_eq!(first.unwrap().primary(), Some("A"));
+    assert!(requests.recv().unwrap().ends_with("g.V().GetLimit(1)"));
+
+}
+
+// == Default Version ==
+
+#[test]
+fn test_default_to_uses_default_version() {
+
+    let (port, requests) = serve("{\"result\":[{\"id\":\"A\"}]}");
+    graph_at(port).find(vertex![ AnyNode => All ]).unwrap();
+    assert!(requests.recv().unwrap().starts_with("POST /api/v1/query/gremlin"));
+
+    match Graph::default_to("local host", 64210) {
+        Err(_) => (),
+        Ok(_) => panic!("should not accept invalid host")
+    }
+
+}
+
+// == Saved Morphisms ==
+
+#[test]
+fn test_save_morphism() {
+
+    let (port, requests) = serve_all(vec!["{\"result\":[{\"id\":\"A\"}]}",
+                                          "{\"result\":[{\"id\":\"B\"}]}"]);
+    let mut graph = graph_at(port);
+    graph.save(morphism![ "follows" -> OutP(Predicate("follows")) ]);
+    graph.save(morphism![ "fof" -> OutP(Predicate("follows")) -> OutP(Predicate("follows")) ]);
+    graph.save(morphism![ "follows" -> InP(Predicate("follows")) ]);
+
+    graph.find(vertex![ Node("C") => All ]).unwrap();
+    graph.find(vertex![ Node("D") -> OutP(Predicate("status")) => All ]).unwrap();
+
+    let first = requests.recv().unwrap();
+    // the later `follows` replaced the earlier one, not declared along with it
+    assert_eq!(first.matches("var follows").count(), 1);
+    assert!(!first.contains("var follows = g.M().Out"));
+    assert!(first.ends_with("var fof = g.M().Out(\"follows\").Out(\"follows\");\
+                             var follows = g.M().In(\"follows\");\
+                             g.V(\"C\").All()"));
+    assert!(requests.recv().unwrap().ends_with("var fof = g.M().Out(\"follows\").Out(\"follows\");\
+                                                var follows = g.M().In(\"follows\");\
+                                                g.V(\"D\").Out(\"status\").All()"));
+
+}
+
+#[test]
+fn test_graph_to_gremlin() {
+
+    let (port, requests) = serve("{\"result\":[]}");
+    let mut graph = graph_at(port).with_root_name("graph");
+    graph.save(Morphism::compile_reuse_at("graph", "follows", vec![ OutP(Predicate("follows")) ].into_boxed_slice()).unwrap());
+
+    let query = Vertex::compile_query_at("graph", Node("C"), vec![ OutP(Predicate("status")) ].into_boxed_slice(), All).unwrap();
+    let gremlin = graph.to_gremlin(&query).unwrap();
+    assert_eq!(gremlin.as_slice(), "var follows = graph.M().Out(\"follows\");graph.V(\"C\").Out(\"status\").All()");
+
+    graph.find(query).unwrap();
+    assert!(requests.recv().unwrap().ends_with(&format!("\r\n\r\n{}", gremlin)));
+
+}
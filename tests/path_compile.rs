@@ -4,12 +4,18 @@
 #[phase(plugin, link)]
 extern crate cayley;
 
+use std::cell::Cell;
+
+use cayley::Graph;
 use cayley::selectors::*;
 
 //use cayley::path::{Path, Route, Query, Reuse};
-use cayley::path::{Trail, Vertex, Morphism};
+use cayley::path::{Trail, Vertex, Morphism, Traverse};
+use cayley::path::validate_query;
 use cayley::path::Traversal::*;
 use cayley::path::Final::*;
+use cayley::path::Comparison;
+use cayley::path::Comparison::{Gt, Lt, Gte, Lte};
 
 macro_rules! path_eq(
     ($src:expr, $res:expr) => ( assert_eq!($src.prefix + $src.value, $res.to_string()); );
@@ -43,6 +49,92 @@ fn test_basic_vertices() {
 
 }
 
+#[test]
+#[cfg(feature = "canonical_selectors")]
+fn test_canonical_selectors() {
+
+    path_eq!(vertex![ Nodes(vec!("foo", "bar", "foo")) => All ], "g.V(\"bar\",\"foo\").All()");
+
+    path_eq!(vertex![ Nodes(vec!("foo", "bar")) -> Out(Predicates(vec!("status", "follows")), AnyTag) ],
+             vertex![ Nodes(vec!("bar", "foo")) -> Out(Predicates(vec!("follows", "status")), AnyTag) ].value);
+
+}
+
+#[test]
+fn test_number_vertices() {
+
+    path_eq!(vertex![ Float(1.5) ], "g.V(\"1.5\")");
+    path_eq!(vertex![ Float(0.1) ], "g.V(\"0.1\")");
+    path_eq!(vertex![ Float(42.0) ], "g.V(\"42\")");
+    path_eq!(vertex![ Float(-0.25) -> Is(Float(2.5e-7)) ], "g.V(\"-0.25\").Is(\"0.00000025\")");
+
+    path_eq!(vertex![ Decimal(1.5, 2) ], "g.V(\"1.50\")");
+    path_eq!(vertex![ Decimal(1.005, 0) ], "g.V(\"1\")");
+    path_eq!(vertex![ Decimal(2.0 / 3.0, 3) ], "g.V(\"0.667\")");
+
+}
+
+#[test]
+fn test_selectors_from_names() {
+
+    let names = ["foo", "bar"];
+
+    path_eq!(vertex![ NodeSelector::from(&names[..]) ], "g.V(\"foo\",\"bar\")");
+    path_eq!(vertex![ names.iter().cloned().collect::<NodeSelector>() ], "g.V(\"foo\",\"bar\")");
+    path_eq!(vertex![ AnyNode -> Out(PredicateSelector::from(&names[..]), TagSelector::from(&["tag"][..])) ],
+             "g.V().Out([\"foo\",\"bar\"],[\"tag\"])");
+    path_eq!(vertex![ AnyNode -> OutP(names.iter().map(|name| *name).collect::<PredicateSelector>()) ],
+             "g.V().Out([\"foo\",\"bar\"])");
+
+}
+
+#[test]
+fn test_owned_vertices() {
+
+    let ids: Vec<String> = vec!["foo".to_string(), "bar".to_string()];
+
+    path_eq!(vertex![ NodeOwned(ids[0].clone()) ], "g.V(\"foo\")");
+    path_eq!(vertex![ NodesOwned(ids.clone()) ], "g.V(\"foo\",\"bar\")");
+    path_eq!(vertex![ AnyNode -> Is(NodesOwned(ids.clone())) ], "g.V().Is(\"foo\",\"bar\")");
+    path_eq!(vertex![ AnyNode -> Has(Predicate("follows"), NodeOwned(ids[1].clone())) ],
+             "g.V().Has(\"follows\",\"bar\")");
+
+}
+
+#[test]
+fn test_typed_vertices() {
+
+    path_eq!(vertex![ Iri("http://example/Bob") ], "g.V(\"<http://example/Bob>\")");
+    path_eq!(vertex![ Iri("<http://example/Bob>") ], "g.V(\"<http://example/Bob>\")");
+    path_eq!(vertex![ Literal("42", "xsd:integer") ],
+             "g.V(\"\\\"42\\\"^^<http://www.w3.org/2001/XMLSchema#integer>\")");
+    path_eq!(vertex![ AnyNode -> Has(Predicate("<http://example/age>"), Literal("42", "<xsd:integer>")) ],
+             "g.V().Has(\"<http://example/age>\",\"\\\"42\\\"^^<http://www.w3.org/2001/XMLSchema#integer>\")");
+    path_eq!(vertex![ Literal("42", "http://example/number") ], "g.V(\"\\\"42\\\"^^<http://example/number>\")");
+    // the quote would end the literal early, the backslash would escape its closing quote
+    path_eq!(vertex![ Literal("say \"hi\"", "xsd:string") ],
+             "g.V(\"\\\"say \\\\\\\"hi\\\\\\\"\\\"^^<http://www.w3.org/2001/XMLSchema#string>\")");
+    path_eq!(vertex![ Literal("C:\\", "xsd:string") ],
+             "g.V(\"\\\"C:\\\\\\\\\\\"^^<http://www.w3.org/2001/XMLSchema#string>\")");
+    path_eq!(vertex![ AnyNode -> Is(Iri("http://example/Bob")) ], "g.V().Is(\"<http://example/Bob>\")");
+
+}
+
+#[test]
+fn test_escaped_names() {
+
+    path_eq!(vertex![ Node("O'Brien \"Jr\"") ], "g.V(\"O'Brien \\\"Jr\\\"\")");
+    path_eq!(vertex![ Node("C:\\path") -> Is(Node("line\nbreak")) ],
+             "g.V(\"C:\\\\path\").Is(\"line\\nbreak\")");
+    path_eq!(vertex![ Nodes(vec!("\"a\"", "b")) -> As(Tag("t\"ag")) ],
+             "g.V(\"\\\"a\\\"\",\"b\").As(\"t\\\"ag\")");
+    path_eq!(vertex![ AnyNode -> Has(Predicate("na\"me"), Node("Amélie \u{1}")) ],
+             "g.V().Has(\"na\\\"me\",\"Amélie \\u0001\")");
+    path_eq!(vertex![ Node("東京") -> Out(Predicate("\\"), Tag("\"")) ],
+             "g.V(\"東京\").Out(\"\\\\\",\"\\\"\")");
+
+}
+
 // == Morphism ==
 
 #[test]
@@ -88,6 +180,9 @@ fn test_path_out() {
     path_eq!(vertex![ Node("D") -> Out(Predicates(vec!("follows", "status")), AnyTag) ],
              "g.V(\"D\").Out([\"follows\",\"status\"])");
 
+    path_eq!(vertex![ Node("D") -> Out(Predicates(vec!("follows", "status")), Tag("via")) ],
+             "g.V(\"D\").Out([\"follows\",\"status\"],\"via\")");
+
     path_eq!(vertex![ Node("D") -> Out(Route(&vertex![ Node("status") ]), Tag("pred")) ],
              "g.V(\"D\").Out(g.V(\"status\"), \"pred\")");
 
@@ -101,6 +196,38 @@ fn test_path_out() {
 
 }
 
+/* path.Out with excluded predicates */
+
+#[test]
+fn test_path_out_not() {
+
+    path_eq!(vertex![ Node("D") -> OutP(Not(vec!("status", "name"))) ],
+             "g.V(\"D\").Out(g.V().Except(g.V(\"status\",\"name\")))");
+
+    path_eq!(vertex![ Node("D") -> In(Not(vec!("status")), Tag("pred")) ],
+             "g.V(\"D\").In(g.V().Except(g.V(\"status\")), \"pred\")");
+
+    path_eq!(vertex![ AnyNode -> Has(Not(vec!("status")), Node("B")) ],
+             "g.V().Has(g.V().Except(g.V(\"status\")),\"B\")");
+
+}
+
+/* path.Out with predicates matching a pattern */
+
+#[test]
+fn test_path_out_regex() {
+
+    path_eq!(vertex![ Node("Casablanca") -> OutP(Regex("^/film/")) ],
+             "g.V(\"Casablanca\").Out(g.V().Filter(regex(\"^/film/\")))");
+
+    path_eq!(vertex![ Node("D") -> In(Regex("^stat"), Tag("pred")) => All ],
+             "g.V(\"D\").In(g.V().Filter(regex(\"^stat\")), \"pred\").All()");
+
+    path_eq!(vertex![ AnyNode -> Has(Regex("name$"), Node("B")) ],
+             "g.V().Has(g.V().Filter(regex(\"name$\")),\"B\")");
+
+}
+
 /* path.In */
 
 #[test]
@@ -127,6 +254,9 @@ fn test_path_both() {
     path_eq!(vertex![ Node("F") -> Both(Predicate("follows"), AnyTag) ],
              "g.V(\"F\").Both(\"follows\")");
 
+    path_eq!(vertex![ Node("F") -> BothVia(Predicate("follows")) ],
+             "g.V(\"F\").Both(\"follows\").Unique()");
+
 }
 
 /* path.Is */
@@ -144,6 +274,24 @@ fn test_path_is() {
 
 }
 
+/* path.Out + path.Is */
+
+#[test]
+fn test_path_out_in_is() {
+
+    path_eq!(vertex![ Node("D") -> OutIs(Predicate("follows"), Node("B")) ],
+             "g.V(\"D\").Out(\"follows\").Is(\"B\")");
+
+    let predicates = vertex![ Node("status") -> OutP(Predicate("foo")) ];
+
+    path_eq!(vertex![ Node("E") -> OutIs(Route(&predicates), Nodes(vec!("B", "C"))) ],
+             "g.V(\"E\").Out(g.V(\"status\").Out(\"foo\")).Is(\"B\",\"C\")");
+
+    path_eq!(vertex![ Node("B") -> InIs(Route(&predicates), Node("A")) ],
+             "g.V(\"B\").In(g.V(\"status\").Out(\"foo\")).Is(\"A\")");
+
+}
+
 /* path.Has */
 
 #[test]
@@ -152,6 +300,76 @@ fn test_path_has() {
     path_eq!(vertex![ AnyNode -> Has(Predicate("follows"), Node("B")) ],
              "g.V().Has(\"follows\",\"B\")");
 
+    path_eq!(vertex![ AnyNode -> Has(Predicate("rating"), Float(1.5)) ],
+             "g.V().Has(\"rating\",\"1.5\")");
+
+}
+
+/* path.HasR */
+
+#[test]
+fn test_path_has_r() {
+
+    path_eq!(vertex![ AnyNode -> HasR(Predicate("follows"), Node("B")) => All ],
+             "g.V().HasR(\"follows\",\"B\").All()");
+
+}
+
+/* path.HasBoth */
+
+#[test]
+fn test_path_has_both() {
+
+    path_eq!(vertex![ AnyNode -> HasBoth(Predicate("follows"), Node("B")) => All ],
+             "g.V().Has(\"follows\",\"B\").Or(g.V().HasR(\"follows\",\"B\")).All()");
+
+    path_eq!(vertex![ Node("A") -> OutP(Predicate("follows")) -> HasBoth(Predicate("follows"), Node("B"))
+                                -> InP(Predicate("likes")) ],
+             "g.V(\"A\").Out(\"follows\").Has(\"follows\",\"B\").Or(g.V(\"A\").Out(\"follows\").HasR(\"follows\",\"B\")).In(\"likes\")");
+
+    // there is no start node to repeat the path from
+    assert!(Morphism::compile_reuse("both", vec![ HasBoth(Predicate("follows"), Node("B")) ].into_boxed_slice()).is_none());
+
+}
+
+/* path.InPredicates / path.OutPredicates */
+
+#[test]
+fn test_path_predicates() {
+
+    path_eq!(vertex![ Node("Casablanca") -> OutPredicates => All ],
+             "g.V(\"Casablanca\").OutPredicates().All()");
+
+    path_eq!(vertex![ Node("B") -> InPredicates => All ],
+             "g.V(\"B\").InPredicates().All()");
+
+}
+
+/* optional traversals */
+
+#[test]
+fn test_path_maybe() {
+
+    let status: Option<&str> = None;
+    path_eq!(Vertex::start(AnyNode).maybe_has(Predicate("status"), status).All(),
+             "g.V().All()");
+    path_eq!(Vertex::start(AnyNode).maybe_has(Predicate("status"), Some("cool_person")).All(),
+             "g.V().Has(\"status\",\"cool_person\").All()");
+
+    path_eq!(Vertex::start(Node("C")).maybe_out(Some("follows")).maybe_in(None).maybe_is(Some("B")).compile(),
+             "g.V(\"C\").Out(\"follows\").Is(\"B\")");
+    path_eq!(Vertex::start(Node("C")).maybe_out(None).maybe_in(Some("follows")).maybe_is(None).compile(),
+             "g.V(\"C\").In(\"follows\")");
+
+    // `None` adds no traversal at all
+    let mut vertex = Vertex::start(Node("C"));
+    vertex.maybe_out(None).maybe_in(None).maybe_has(Predicate("status"), None).maybe_is(None);
+    assert_eq!(vertex.1.len(), 0);
+
+    let mut morphism = Morphism::start("optional");
+    morphism.maybe_out(Some("follows")).maybe_is(None);
+    assert_eq!(morphism.1.len(), 1);
+
 }
 
 // == Tagging ==
@@ -191,15 +409,26 @@ fn test_path_save() {
     path_eq!(vertex![ Nodes(vec!("D", "B")) -> Save(Predicate("follows"), Tag("target")) ],
              "g.V(\"D\",\"B\").Save(\"follows\",\"target\")");
 
-    /* TODO:
-    path_panic!(V::start(Nodes(vec!("D", "B"))).Save(AnyPredicate, Tag("target")),
-                "should fail to compile path.Save w/AnyPredicate");
-    path_panic!(V::start(Nodes(vec!("D", "B"))).Save(Predicates(vec!("foo", "bar")), Tag("target")),
-                "should fail to compile path.Save w/Predicates");
-    path_panic!(V::start(Nodes(vec!("D", "B"))).Save(Predicate("follows"), AnyTag),
-                "should fail to compile path.Save w/AnyTag");
-    path_panic!(V::start(Nodes(vec!("D", "B"))).Save(Predicate("follows"), Tags(vec!("foo", "bar"))),
-                "should fail to compile path.Save w/AnyTag"); */
+    assert!(Vertex::compile_route(Nodes(vec!("D", "B")), vec![ Save(AnyPredicate, Tag("target")) ].into_boxed_slice()).is_none(),
+            "should fail to compile path.Save w/AnyPredicate");
+    assert!(Vertex::compile_route(Nodes(vec!("D", "B")), vec![ Save(Predicates(vec!("foo", "bar")), Tag("target")) ].into_boxed_slice()).is_none(),
+            "should fail to compile path.Save w/Predicates");
+    assert!(Vertex::compile_query(Nodes(vec!("D", "B")), vec![ Save(Predicate("follows"), AnyTag) ].into_boxed_slice(), All).is_none(),
+            "should fail to compile path.Save w/AnyTag");
+    assert!(Vertex::compile_query(Nodes(vec!("D", "B")), vec![ Save(Predicate("follows"), Tags(vec!("foo", "bar"))) ].into_boxed_slice(), All).is_none(),
+            "should fail to compile path.Save w/Tags");
+    assert!(Morphism::compile_reuse("saving", vec![ SaveOpt(AnyPredicate, AnyTag) ].into_boxed_slice()).is_none(),
+            "should fail to compile path.SaveOpt w/AnyPredicate");
+}
+
+/* path.SaveOpt */
+
+#[test]
+fn test_path_save_opt() {
+
+    path_eq!(vertex![ Nodes(vec!("D", "B")) -> SaveOpt(Predicate("status"), Tag("status")) => All ],
+             "g.V(\"D\",\"B\").SaveOpt(\"status\",\"status\").All()");
+
 }
 
 // == Joining ==
@@ -219,6 +448,21 @@ fn test_path_intersect_and() {
 
 }
 
+/* Morphism + path */
+
+#[test]
+fn test_morphism_add_path() {
+
+    let d_follows = vertex![ Node("D") -> Out(Predicate("follows"), AnyTag) ];
+    let fof = morphism![ "fof" -> OutP(Predicate("follows")) -> OutP(Predicate("follows")) ];
+
+    let cool_fof = fof + path![ Has(Predicate("status"), Node("cool_person")) -> And(&d_follows) ];
+    assert_eq!(cool_fof.name.as_slice(), "fof");
+    path_eq!(cool_fof,
+             "g.M().Out(\"follows\").Out(\"follows\").Has(\"status\",\"cool_person\").And(g.V(\"D\").Out(\"follows\"))");
+
+}
+
 /* path.Union / path.Or */
 
 #[test]
@@ -234,12 +478,141 @@ fn test_path_union_or() {
 
 }
 
+// == Labels ==
+
+/* path.LabelContext */
+
+#[test]
+fn test_path_label_context() {
+
+    path_eq!(vertex![ AnyNode -> LabelContextP(Predicate("source-A")) -> OutP(Predicate("knows")) => All ],
+             "g.V().LabelContext(\"source-A\").Out(\"knows\").All()");
+
+    path_eq!(vertex![ AnyNode -> LabelContext(Predicates(vec!("source-A", "source-B")), Tag("source"))
+                              -> OutP(Predicate("knows")) ],
+             "g.V().LabelContext([\"source-A\",\"source-B\"],\"source\").Out(\"knows\")");
+
+    path_eq!(vertex![ AnyNode -> LabelContextP(AnyPredicate) -> OutP(Predicate("knows")) ],
+             "g.V().LabelContext().Out(\"knows\")");
+
+}
+
+/* path.Labels */
+
+#[test]
+fn test_path_labels() {
+
+    path_eq!(vertex![ Node("X") -> Labels => All ],
+             "g.V(\"X\").Labels().All()");
+
+    path_eq!(vertex![ Node("X") -> LabelContextP(Predicate("source-A")) -> OutP(Predicate("knows")) -> Labels ],
+             "g.V(\"X\").LabelContext(\"source-A\").Out(\"knows\").Labels()");
+
+}
+
+// == Filtering ==
+
+/* path.Filter */
+
+#[test]
+fn test_path_filter() {
+
+    path_eq!(vertex![ Node("X") -> OutP(Predicate("year")) -> Filter(Gt("2000")) => All ],
+             "g.V(\"X\").Out(\"year\").Filter(gt(\"2000\")).All()");
+
+    path_eq!(vertex![ AnyNode -> OutP(Predicate("year")) -> Filter(Gte("1990")) -> Filter(Lt("2000")) => All ],
+             "g.V().Out(\"year\").Filter(gte(\"1990\")).Filter(lt(\"2000\")).All()");
+
+    path_eq!(vertex![ AnyNode -> Filter(Lte("10")) ],
+             "g.V().Filter(lte(\"10\"))");
+
+    path_eq!(vertex![ AnyNode -> OutP(Predicate("name")) -> Filter(Comparison::Regex("^Casa\"blanca")) => All ],
+             "g.V().Out(\"name\").Filter(regex(\"^Casa\\\"blanca\")).All()");
+
+}
+
+// == Paging ==
+
+/* path.Skip / path.Limit */
+
+#[test]
+fn test_path_skip_limit() {
+
+    path_eq!(vertex![ AnyNode -> OutP(Predicate("follows")) -> Skip(10) -> Limit(5) => All ],
+             "g.V().Out(\"follows\").Skip(10).Limit(5).All()");
+
+    path_eq!(vertex![ AnyNode -> Limit(5) -> Intersect(&vertex![ Node("C") -> OutP(Predicate("follows")) ]) ],
+             "g.V().Limit(5).And(g.V(\"C\").Out(\"follows\"))");
+
+}
+
 /* fn test_concatenating_paths() {
     // TODO
 } */
 
+/* path.Unique */
+
+#[test]
+fn test_path_unique() {
+
+    let d_follows = vertex![ Node("D") -> OutP(Predicate("follows")) ];
+
+    path_eq!(vertex![ Node("C") -> OutP(Predicate("follows")) -> Union(&d_follows) -> Unique => All ],
+             "g.V(\"C\").Out(\"follows\").Or(g.V(\"D\").Out(\"follows\")).Unique().All()");
+
+}
+
+/* path.Except */
+
+#[test]
+fn test_path_except() {
+
+    let blocked = vertex![ Node("D") -> OutP(Predicate("follows")) ];
+
+    path_eq!(vertex![ Node("C") -> OutP(Predicate("follows")) -> Except(&blocked) => All ],
+             "g.V(\"C\").Out(\"follows\").Except(g.V(\"D\").Out(\"follows\")).All()");
+
+    let follows = morphism![ "follows" -> OutP(Predicate("follows")) ];
+    let blocked = vertex![ Node("D") -> Follow(&follows) ];
+
+    path_eq!(vertex![ Node("C") -> OutP(Predicate("follows")) -> Except(&blocked) => All ],
+             "var follows = g.M().Out(\"follows\");g.V(\"C\").Out(\"follows\").Except(g.V(\"D\").Follow(follows)).All()");
+
+}
+
+/* path.Without */
+
+#[test]
+fn test_path_without() {
+
+    let blocked = vertex![ Node("D") ];
+
+    path_eq!(Vertex::start(Node("C")).OutP(Predicate("follows")).Without(&blocked).All(),
+             "g.V(\"C\").Out(\"follows\").Except(g.V(\"D\")).All()");
+
+    let follows = morphism![ "follows" -> OutP(Predicate("follows")) ];
+    let blocked = vertex![ Node("D") -> Follow(&follows) ];
+
+    path_eq!(Vertex::start(Node("C")).OutP(Predicate("follows")).Without(&blocked).All(),
+             "var follows = g.M().Out(\"follows\");g.V(\"C\").Out(\"follows\").Except(g.V(\"D\").Follow(follows)).All()");
+
+}
+
 // == Morphisms ==
 
+/* path.Skip / path.Limit in Morphisms */
+
+#[test]
+fn test_morphism_skip_limit() {
+
+    let page = morphism![ "followersPage" -> InP(Predicate("follows")) -> Skip(10) -> Limit(5) ];
+    path_eq!(page, "g.M().In(\"follows\").Skip(10).Limit(5)");
+
+    path_eq!(vertex![ Node("B") -> Follow(&page) => All ],
+             "var followersPage = g.M().In(\"follows\").Skip(10).Limit(5);g.V(\"B\").Follow(followersPage).All()");
+
+}
+
 /* path.Follow */
 
 #[test]
@@ -255,6 +628,82 @@ fn test_path_follow() {
 
 }
 
+/* Morphism equality */
+
+#[test]
+fn test_morphism_equality() {
+
+    let m_1 = morphism![ "fof" -> Out(Predicate("follows"), AnyTag) -> Out(Predicate("follows"), AnyTag) ];
+    let m_2 = morphism![ "fof" -> OutP(Predicate("follows")) -> OutP(Predicate("follows")) ];
+    let m_3 = morphism![ "fof" -> OutP(Predicate("follows")) ];
+    let m_4 = morphism![ "friendOfFriend" -> OutP(Predicate("follows")) -> OutP(Predicate("follows")) ];
+
+    assert!(m_1 == m_2);
+    assert!(m_1 != m_3);
+    assert!(m_1 != m_4);
+
+}
+
+/* Morphism cache */
+
+#[test]
+fn test_morphism_cache() {
+
+    let graph = Graph::default().unwrap();
+    let compiled = Cell::new(0);
+    let compile = || {
+        compiled.set(compiled.get() + 1);
+        morphism![ "friendOfFriend" -> OutP(Predicate("follows")) -> OutP(Predicate("follows")) ]
+    };
+
+    let first = graph.morphism("friendOfFriend", &compile);
+    let second = graph.morphism("friendOfFriend", &compile);
+    assert_eq!(compiled.get(), 1);
+    assert!(*first == *second);
+
+    path_eq!(vertex![ Node("C") -> Follow(&second) ],
+             "var friendOfFriend = g.M().Out(\"follows\").Out(\"follows\");g.V(\"C\").Follow(friendOfFriend)");
+
+    graph.clear_morphism_cache();
+    graph.morphism("friendOfFriend", &compile);
+    assert_eq!(compiled.get(), 2);
+
+}
+
+/* Morphisms declared once */
+
+#[test]
+fn test_morphism_declared_once() {
+
+    let m_1 = morphism![ "m1" -> OutP(Predicate("follows")) ];
+    let m_2 = morphism![ "m2" -> Has(Predicate("status"), Node("cool_person")) -> Follow(&m_1) ];
+
+    let twice = vertex![ Node("C") -> Follow(&m_1) -> FollowR(&m_1) => All ];
+    assert_eq!(twice.prefix.as_slice(), "var m1 = g.M().Out(\"follows\");");
+    path_eq!(twice, "var m1 = g.M().Out(\"follows\");g.V(\"C\").Follow(m1).FollowR(m1).All()");
+
+    let nested = vertex![ Node("C") -> Follow(&m_1) -> Follow(&m_2) -> And(&vertex![ Node("D") -> Follow(&m_1) ]) ];
+    assert_eq!(nested.prefix.as_slice(),
+               "var m1 = g.M().Out(\"follows\");var m2 = g.M().Has(\"status\",\"cool_person\").Follow(m1);");
+
+}
+
+/* Morphism name clash */
+
+#[test]
+fn test_morphism_name_clash() {
+
+    let follows = morphism![ "m1" -> OutP(Predicate("follows")) ];
+    let status = morphism![ "m1" -> OutP(Predicate("status")) ];
+    assert!(Vertex::compile_query(Node("C"), vec![ Follow(&follows), Follow(&status) ].into_boxed_slice(), All).is_none(),
+            "should fail to compile two different Morphisms with the same name");
+
+    let uses_status = morphism![ "m2" -> Follow(&status) ];
+    assert!(Vertex::compile_route(Node("C"), vec![ Follow(&follows), Follow(&uses_status) ].into_boxed_slice()).is_none(),
+            "should fail to compile a clash inside a used Morphism");
+
+}
+
 /* path.FollowR */
 
 #[test]
@@ -280,6 +729,174 @@ fn test_query_finals() {
     path_eq!(vertex![ Node("foo") -> Out(Predicate("follows"), AnyTag) => GetLimit(5) ],
              "g.V(\"foo\").Out(\"follows\").GetLimit(5)");
 
+    path_eq!(vertex![ AnyNode -> OutP(Predicate("follows")) => Count ],
+             "g.V().Out(\"follows\").Count()");
+
+    path_eq!(vertex![ AnyNode => First ], "g.V().GetLimit(1)");
+    path_eq!(vertex![ Node("foo") -> InP(Predicate("follows")) => FirstN(3) ],
+             "g.V(\"foo\").In(\"follows\").GetLimit(3)");
+    path_eq!(Vertex::start(AnyNode).First(), "g.V().GetLimit(1)");
+
+    path_eq!(vertex![ Node("Casablanca") => ForEach("g.Emit(d)") ],
+             "g.V(\"Casablanca\").ForEach(function(d){ g.Emit(d) })");
+
+}
+
+#[test]
+fn test_query_malformed_for_each() {
+
+    // closes the callback early and runs outside of it
+    assert!(Vertex::compile_query(AnyNode, vec![].into_boxed_slice(), ForEach("g.Emit(d) }); g.V().All(); ({")).is_none(),
+            "should not compile a body closing the callback");
+    assert!(Vertex::compile_query(AnyNode, vec![].into_boxed_slice(), ForEach("g.Emit(d")).is_none(),
+            "should not compile a body with unclosed parenthesis");
+    assert!(Vertex::compile_query(AnyNode, vec![].into_boxed_slice(), ForEach("g.Emit(\"d)")).is_none(),
+            "should not compile a body with unclosed quote");
+    // brackets inside the quotes don't count
+    path_eq!(vertex![ AnyNode => ForEach("g.Emit(\"})\")") ],
+             "g.V().ForEach(function(d){ g.Emit(\"})\") })");
+
+}
+
+#[test]
+fn test_query_non_positive_limit() {
+
+    assert!(Vertex::compile_query(AnyNode, vec![].into_boxed_slice(), GetLimit(0)).is_none(),
+            "should not compile GetLimit(0)");
+    assert!(Vertex::compile_query(AnyNode, vec![].into_boxed_slice(), GetLimit(-1)).is_none(),
+            "should not compile GetLimit(-1)");
+    assert!(Vertex::compile_query(AnyNode, vec![].into_boxed_slice(), FirstN(0)).is_none(),
+            "should not compile FirstN(0)");
+    assert!(Vertex::compile_query(AnyNode, vec![].into_boxed_slice(), GetLimit(1)).is_some(),
+            "should compile GetLimit(1)");
+
+}
+
+#[test]
+#[should_panic]
+fn test_query_zero_limit_macro() {
+
+    vertex![ AnyNode => GetLimit(0) ];
+
+}
+
+#[test]
+fn test_reusing_final() {
+
+    let limit = GetLimit(10);
+    assert_eq!(limit, GetLimit(10));
+    assert!(limit != FirstN(10));
+    assert_eq!(format!("{:?}", limit), "GetLimit(10)");
+
+    let foo = Vertex::compile_query(Node("foo"), vec![ OutP(Predicate("follows")) ].into_boxed_slice(), limit.clone()).unwrap();
+    let bar = Vertex::compile_query(Node("bar"), vec![ InP(Predicate("follows")) ].into_boxed_slice(), limit).unwrap();
+    path_eq!(foo, "g.V(\"foo\").Out(\"follows\").GetLimit(10)");
+    path_eq!(bar, "g.V(\"bar\").In(\"follows\").GetLimit(10)");
+
+}
+
+// == Components ==
+
+#[test]
+fn test_to_gremlin() {
+
+    let fof = morphism![ "fof" -> OutP(Predicate("follows")) -> OutP(Predicate("follows")) ];
+    let cool = vertex![ AnyNode -> Has(Predicate("status"), Node("cool_person")) ];
+    let cool_fof = morphism![ "coolFof" -> Follow(&fof) -> And(&cool) ];
+
+    assert_eq!(cool_fof.to_gremlin().as_slice(),
+               "var fof = g.M().Out(\"follows\").Out(\"follows\");\
+                var coolFof = g.M().Follow(fof).And(g.V().Has(\"status\",\"cool_person\"));");
+    assert_eq!(cool.to_gremlin().as_slice(), "g.V().Has(\"status\",\"cool_person\")");
+
+    let query = vertex![ Node("C") -> Follow(&cool_fof) => All ];
+    assert_eq!(query.to_gremlin(), query.prefix.clone() + &query.value);
+    assert_eq!(query.to_gremlin().as_slice(),
+               "var fof = g.M().Out(\"follows\").Out(\"follows\");\
+                var coolFof = g.M().Follow(fof).And(g.V().Has(\"status\",\"cool_person\"));\
+                g.V(\"C\").Follow(coolFof).All()");
+
+}
+
+#[test]
+fn test_query_components() {
+
+    let m = morphism![ "m" -> OutP(Predicate("foo")) ];
+    let components = vertex![ Node("bar") -> Follow(&m) -> Has(Predicate("buz"), AnyNode) => GetLimit(10) ].components();
+
+    assert_eq!(components.prefix.as_slice(), "var m = g.M().Out(\"foo\");");
+    assert_eq!(components.root.as_slice(), "g.V(\"bar\")");
+    assert_eq!(components.traversals.as_slice(), ".Follow(m).Has(\"buz\")");
+    assert_eq!(components._final.as_slice(), ".GetLimit(10)");
+
+    let components = vertex![ AnyNode => All ].components();
+    assert_eq!(components.prefix.as_slice(), "");
+    assert_eq!(components.traversals.as_slice(), "");
+
+    let components = vertex![ Node("a.b(") -> OutP(Predicate("c).d")) => ForEach("g.Emit(d.id)") ].components();
+    assert_eq!(components.root.as_slice(), "g.V(\"a.b(\")");
+    assert_eq!(components.traversals.as_slice(), ".Out(\"c).d\")");
+    assert_eq!(components._final.as_slice(), ".ForEach(function(d){ g.Emit(d.id) })");
+
+    let mut query = vertex![ Node("bar") -> OutP(Predicate("foo")) => All ];
+    query.value = "g.V(\"baz\").In(\"foo\").Count()".to_string();
+    let components = query.components();
+    assert_eq!(components.root.as_slice(), "g.V(\"baz\")");
+    assert_eq!(components.traversals.as_slice(), ".In(\"foo\")");
+    assert_eq!(components._final.as_slice(), ".Count()");
+
+    let query = cayley::path::CompiledQuery { prefix: "".to_string(), value: "g.V().Out(\"foo\")".to_string(),
+                                              expectation: cayley::path::Expectation::Unknown };
+    assert_eq!(query.components()._final.as_slice(), "");
+    assert_eq!(query.components().traversals.as_slice(), ".Out(\"foo\")");
+
+}
+
+// == Validation ==
+
+#[test]
+fn test_query_validation() {
+
+    let query = vertex![ Node("foo") -> Out(Predicate("follows"), Tags(vec!("a", "b"))) => All ];
+    assert_eq!(validate_query((query.prefix + &query.value).as_str()), Ok(()));
+
+    assert_eq!(validate_query("g.V(\"a(b\").All()"), Ok(()));
+    assert_eq!(validate_query("g.V(\"a\\\"b\").All()"), Ok(()));
+
+    assert_eq!(validate_query("g.V(\"foo\").Out(\"follows\".All()"), Err(14));
+    assert_eq!(validate_query("g.V(\"foo\")).All()"), Err(10));
+    assert_eq!(validate_query("g.V(\"foo).All()"), Err(4));
+    assert_eq!(validate_query("g.V().Out([\"a\",\"b\")"), Err(18));
+
+    let escaped = vertex![ Node("fo\"o") => All ];
+    assert_eq!(validate_query((escaped.prefix + &escaped.value).as_str()), Ok(()));
+
+    assert!(validate_query("g.V(\"fo\"o\").All()").is_err());
+
+}
+
+// == Root name ==
+
+#[test]
+fn test_root_name() {
+
+    let follows = Morphism::compile_reuse_at("graph", "follows", vec![ OutP(Predicate("follows")) ].into_boxed_slice()).unwrap();
+    assert_eq!(follows.value.as_slice(), "graph.M().Out(\"follows\")");
+
+    path_eq!(Vertex::compile_query_at("graph", Node("C"), vec![ Follow(&follows) ].into_boxed_slice(), All).unwrap(),
+             "var follows = graph.M().Out(\"follows\");graph.V(\"C\").Follow(follows).All()");
+
+    // the routes standing for the predicates start with the root name as well
+    path_eq!(Vertex::compile_route_at("graph", Node("D"), vec![ OutP(Not(vec!("status"))),
+                                                              InP(Regex("^/film/")) ].into_boxed_slice()).unwrap(),
+             "graph.V(\"D\").Out(graph.V().Except(graph.V(\"status\"))).In(graph.V().Filter(regex(\"^/film/\")))");
+    path_eq!(Trail::compile_path_at("graph", vec![ HasR(Not(vec!("status")), Node("B")) ].into_boxed_slice()).unwrap(),
+             ".HasR(graph.V().Except(graph.V(\"status\")),\"B\")");
+
+    // the same traversals compiled as usual start with `g`
+    path_eq!(Vertex::compile_query(Node("C"), vec![ OutP(Not(vec!("status"))) ].into_boxed_slice(), All).unwrap(),
+             "g.V(\"C\").Out(g.V().Except(g.V(\"status\"))).All()");
+
 }
 
 // == Other ==
@@ -299,6 +916,29 @@ fn test_inclusive_vertices() {
 
 }
 
+#[test]
+fn test_morphisms_with_routes() {
+
+    let fof = morphism![ "fof" -> OutP(Predicate("follows")) -> OutP(Predicate("follows")) ];
+    let cool = vertex![ AnyNode -> Has(Predicate("status"), Node("cool_person")) -> Follow(&fof) ];
+    let cool_fof = morphism![ "coolFof" -> Follow(&fof) -> And(&cool) ];
+
+    // the route's prefix becomes the Morphism's prefix
+    assert_eq!(cool_fof.prefix.as_slice(), "var fof = g.M().Out(\"follows\").Out(\"follows\");");
+    assert_eq!(cool_fof.value.as_slice(),
+               "g.M().Follow(fof).And(g.V().Has(\"status\",\"cool_person\").Follow(fof))");
+
+    // the Morphisms used by the route are declared before the Morphism using it
+    let blocked = vertex![ Node("D") -> Follow(&fof) ];
+    let unblocked = morphism![ "unblocked" -> OutP(Predicate("follows")) -> Except(&blocked) ];
+    path_eq!(vertex![ Node("C") -> Follow(&unblocked) -> Follow(&cool_fof) => All ],
+             "var fof = g.M().Out(\"follows\").Out(\"follows\");\
+              var unblocked = g.M().Out(\"follows\").Except(g.V(\"D\").Follow(fof));\
+              var coolFof = g.M().Follow(fof).And(g.V().Has(\"status\",\"cool_person\").Follow(fof));\
+              g.V(\"C\").Follow(unblocked).Follow(coolFof).All()");
+
+}
+
 #[test]
 fn test_inclusive_moprphisms() {
 
@@ -315,16 +955,70 @@ fn test_inclusive_moprphisms() {
 
 }
 
-/* TODO:
+// == Builders ==
+
+#[test]
+fn test_morphism_builder() {
+
+    let mut friend_of_friend = Morphism::start("friendOfFriend");
+            friend_of_friend.OutP(Predicate("follows")).OutP(Predicate("follows"));
+
+    path_eq!(friend_of_friend.compile(),
+             "var friendOfFriend = g.M().Out(\"follows\").Out(\"follows\");");
+
+    assert!(friend_of_friend.compile() ==
+            morphism![ "friendOfFriend" -> OutP(Predicate("follows")) -> OutP(Predicate("follows")) ]);
+
+}
+
+#[test]
+fn test_vertex_builder() {
 
-path_eq!(V::start(Node("bar")).In(Predicate("follows"), AnyTag).ToArray(),
-         "g.V(\"bar\").In(\"follows\").toArray()");
+    let friend_of_friend = morphism![ "friendOfFriend" -> OutP(Predicate("follows"))
+                                                       -> OutP(Predicate("follows")) ];
 
-path_eq!(V::start(AnyNode).Out(Predicate("follows"), AnyTag).ToValue(),
-         "g.V().Out(\"follows\").ToValue()");
+    let mut cool = Vertex::start(Node("C"));
+            cool.Follow(&friend_of_friend).Has(Predicate("status"), Node("cool_person"));
 
-path_eq!(V::start(Node("foo")).Out(Predicate("follows"), AnyTag).TagValue(),
-         "g.V(\"foo\").Out(\"follows\").TagValue()");
+    path_eq!(cool.compile(),
+             "var friendOfFriend = g.M().Out(\"follows\").Out(\"follows\");g.V(\"C\").Follow(friendOfFriend).Has(\"status\",\"cool_person\")");
+
+    path_eq!(cool.All(),
+             "var friendOfFriend = g.M().Out(\"follows\").Out(\"follows\");g.V(\"C\").Follow(friendOfFriend).Has(\"status\",\"cool_person\").All()");
+
+    let mut blocked = Vertex::start(Node("D"));
+            blocked.OutP(Predicate("blocks"));
+    let blocked = blocked.compile();
+
+    path_eq!(Vertex::start(Node("C")).OutP(Predicate("follows")).Except(&blocked).All(),
+             "g.V(\"C\").Out(\"follows\").Except(g.V(\"D\").Out(\"blocks\")).All()");
+
+}
+
+#[test]
+fn test_vertex_builder_finals() {
+
+    path_eq!(Vertex::start(Node("bar")).In(Predicate("follows"), AnyTag).ToArray(),
+             "g.V(\"bar\").In(\"follows\").ToArray()");
+
+    path_eq!(Vertex::start(AnyNode).Out(Predicate("follows"), AnyTag).ToValue(),
+             "g.V().Out(\"follows\").ToValue()");
+
+    path_eq!(Vertex::start(Node("foo")).Out(Predicate("follows"), AnyTag).TagValue(),
+             "g.V(\"foo\").Out(\"follows\").TagValue()");
+
+    path_eq!(Vertex::start(Node("foo")).OutT(Tag("target")).TagArray(),
+             "g.V(\"foo\").Out(null,\"target\").TagArray()");
+
+    path_eq!(Vertex::start(Node("foo")).InP(Predicate("follows")).GetLimit(5),
+             "g.V(\"foo\").In(\"follows\").GetLimit(5)");
+
+    path_eq!(Vertex::start(Node("foo")).InP(Predicate("follows")).Count(),
+             "g.V(\"foo\").In(\"follows\").Count()");
+
+}
+
+/* TODO:
 
 query.ForEach(callback), query.ForEach(limit, callback); */
 
@@ -0,0 +1,31 @@
+extern crate cayley;
+
+use cayley::Quad;
+use cayley::Error::InvalidQuad;
+
+#[test]
+fn test_quad_validation() {
+
+    let quad = Quad::new("A", "follows", "B").unwrap();
+    assert_eq!(quad.subject.as_slice(), "A");
+    assert_eq!(quad.predicate.as_slice(), "follows");
+    assert_eq!(quad.object.as_slice(), "B");
+    assert_eq!(quad.label, None);
+
+    let labeled = Quad::with_label("A", "follows", "B", "social").unwrap();
+    assert_eq!(labeled.label, Some("social".to_string()));
+
+    match Quad::new("", "follows", "B") {
+        Err(InvalidQuad(part)) => assert_eq!(part.as_slice(), "subject"), _ => panic!("empty subject")
+    }
+    match Quad::new("A", "  ", "B") {
+        Err(InvalidQuad(part)) => assert_eq!(part.as_slice(), "predicate"), _ => panic!("blank predicate")
+    }
+    match Quad::new("A", "follows", "\t") {
+        Err(InvalidQuad(part)) => assert_eq!(part.as_slice(), "object"), _ => panic!("blank object")
+    }
+    match Quad::with_label("A", "follows", "B", "") {
+        Err(InvalidQuad(part)) => assert_eq!(part.as_slice(), "label"), _ => panic!("empty label")
+    }
+
+}